@@ -0,0 +1,79 @@
+//! The candidate pool of a movie night, built from the selections of its participants.
+
+use std::collections::HashMap;
+
+use crate::services::froovie_service::{Movie, MovieNight, Selections, Veto};
+
+/// A movie proposed for a movie night.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Candidate {
+    pub movie: Movie,
+    /// The participants going to the movie night who have the movie in their selection, in participant order.
+    pub proposed_by: Vec<i32>,
+    /// Kept in the pool by the host, and listed first.
+    pub pinned: bool,
+    /// Taken out of the pool by the host. Excluded candidates cannot be voted for.
+    pub excluded: bool,
+    /// Vetoes cast by participants. Vetoed candidates cannot be voted for either.
+    pub vetoes: Vec<Veto>,
+}
+
+impl Candidate {
+    pub fn moviedb_id(&self) -> i32 {
+        self.movie.moviedb_id
+    }
+
+    /// Whether the candidate takes part in the vote.
+    pub fn is_eligible(&self) -> bool {
+        !self.excluded && !self.is_vetoed()
+    }
+
+    pub fn is_vetoed(&self) -> bool {
+        !self.vetoes.is_empty()
+    }
+}
+
+/// Merges the selections of the participants going to the movie night into one candidate per movie,
+/// identified by its moviedb id.
+///
+/// Pinned candidates come first and the ones that cannot be voted for last,
+/// the others are ordered by how many participants want them, then by title.
+pub fn pool(movie_night: &MovieNight, selections: &[(i32, Selections)]) -> Vec<Candidate> {
+    let mut candidates: Vec<Candidate> = Vec::new();
+    let mut positions: HashMap<i32, usize> = HashMap::new();
+
+    let going = movie_night.going();
+    for (participant, selection) in selections.iter().filter(|(participant, _)| going.contains(participant)) {
+        for movie in &selection.movies {
+            match positions.get(&movie.moviedb_id) {
+                Some(&position) => {
+                    let proposed_by = &mut candidates[position].proposed_by;
+                    if !proposed_by.contains(participant) {
+                        proposed_by.push(*participant);
+                    }
+                }
+                None => {
+                    positions.insert(movie.moviedb_id, candidates.len());
+                    candidates.push(Candidate {
+                        movie: movie.clone(),
+                        proposed_by: vec![*participant],
+                        pinned: movie_night.pinned.contains(&movie.moviedb_id),
+                        excluded: movie_night.excluded.contains(&movie.moviedb_id),
+                        vetoes: movie_night.vetoes.iter()
+                            .filter(|veto| veto.moviedb_id == movie.moviedb_id)
+                            .cloned()
+                            .collect(),
+                    });
+                }
+            }
+        }
+    }
+
+    candidates.sort_by(|a, b| {
+        b.pinned.cmp(&a.pinned)
+            .then(b.is_eligible().cmp(&a.is_eligible()))
+            .then(b.proposed_by.len().cmp(&a.proposed_by.len()))
+            .then(a.movie.title.cmp(&b.movie.title))
+    });
+    candidates
+}
@@ -0,0 +1,231 @@
+use failure::Error;
+use yew::{html, Callback, Component, ComponentLink, Html, Renderable, ShouldRender};
+use yew::services::fetch::FetchTask;
+
+use crate::candidates::Candidate;
+use crate::router::Mount;
+use crate::routes;
+use crate::services::froovie_service::{CandidateChoices, FroovieService, MovieNight, Veto};
+use crate::services::session;
+
+/// The candidate pool of a movie night, where the host can pin or exclude candidates
+/// and participants can veto them.
+pub struct CandidatesModel {
+    props: Props,
+    froovie: FroovieService,
+    callback: Callback<Result<MovieNight, Error>>,
+    task: Option<FetchTask>,
+    error: Option<String>,
+}
+
+#[derive(Clone, PartialEq, Default)]
+pub struct Props {
+    pub movie_night: MovieNight,
+    pub candidates: Vec<Candidate>,
+    /// Whether the selections of some participants are still loading.
+    pub loading: bool,
+    pub is_host: bool,
+    pub is_participant: bool,
+    /// How many more candidates the current user can veto.
+    pub vetoes_left: u32,
+    /// Where the candidates are displayed, to link to them.
+    pub mount: Mount,
+    /// Id of the candidate to highlight, taken from the url fragment.
+    pub highlight: Option<String>,
+    /// Called with the movie night once the pool changed.
+    pub onupdate: Option<Callback<MovieNight>>,
+}
+
+pub enum Msg {
+    TogglePinned(i32),
+    ToggleExcluded(i32),
+    Veto(i32),
+    Share(i32),
+    Updated(Result<MovieNight, Error>),
+}
+
+/// The id of a candidate's element, usable as a url fragment linking to it.
+pub fn candidate_anchor(moviedb_id: i32) -> String {
+    format!("candidate-{}", moviedb_id)
+}
+
+/// Adds the id to the list if it is missing, removes it otherwise.
+fn toggle(ids: &[i32], id: i32) -> Vec<i32> {
+    if ids.contains(&id) {
+        ids.iter().cloned().filter(|other| *other != id).collect()
+    } else {
+        let mut ids = ids.to_vec();
+        ids.push(id);
+        ids
+    }
+}
+
+/// Who vetoed a candidate, as far as we know.
+fn view_vetoes(vetoes: &[Veto]) -> String {
+    let voters: Vec<String> = vetoes.iter()
+        .filter_map(|veto| veto.voter)
+        .map(|voter| voter.to_string())
+        .collect();
+    if voters.len() == vetoes.len() {
+        format!("Vetoed by {}", voters.join(", "))
+    } else {
+        format!("Vetoed {} times", vetoes.len())
+    }
+}
+
+impl CandidatesModel {
+    fn update_choices(&mut self, choices: CandidateChoices) {
+        let task = self.froovie.update_candidates(self.props.movie_night.id, &choices, self.callback.clone());
+        self.task = Some(task);
+    }
+}
+
+impl Component for CandidatesModel {
+    type Message = Msg;
+    type Properties = Props;
+
+    fn create(props: Self::Properties, mut link: ComponentLink<Self>) -> Self {
+        CandidatesModel {
+            props,
+            froovie: FroovieService::new(),
+            callback: link.send_back(Msg::Updated),
+            task: None,
+            error: None,
+        }
+    }
+
+    fn update(&mut self, msg: Self::Message) -> ShouldRender {
+        let movie_night = &self.props.movie_night;
+        match msg {
+            // A candidate is either pinned or excluded, never both.
+            Msg::TogglePinned(moviedb_id) => {
+                let choices = CandidateChoices {
+                    pinned: toggle(&movie_night.pinned, moviedb_id),
+                    excluded: movie_night.excluded.iter().cloned().filter(|id| *id != moviedb_id).collect(),
+                };
+                self.update_choices(choices);
+            }
+            Msg::ToggleExcluded(moviedb_id) => {
+                let choices = CandidateChoices {
+                    pinned: movie_night.pinned.iter().cloned().filter(|id| *id != moviedb_id).collect(),
+                    excluded: toggle(&movie_night.excluded, moviedb_id),
+                };
+                self.update_choices(choices);
+            }
+            Msg::Veto(moviedb_id) => {
+                let veto = Veto {
+                    moviedb_id,
+                    voter: Some(session::current_user_id()),
+                };
+                let task = self.froovie.veto_candidate(movie_night.id, &veto, self.callback.clone());
+                self.task = Some(task);
+            }
+            Msg::Share(moviedb_id) => {
+                let mut route = self.props.mount.route("");
+                route.fragment = Some(candidate_anchor(moviedb_id));
+                routes::copy_link(&route);
+                return false;
+            }
+            Msg::Updated(Ok(movie_night)) => {
+                self.task = None;
+                self.error = None;
+                if let Some(ref onupdate) = self.props.onupdate {
+                    onupdate.emit(movie_night);
+                }
+            }
+            Msg::Updated(Err(error)) => {
+                self.task = None;
+                self.error = Some(error.to_string());
+            }
+        }
+        true
+    }
+
+    fn change(&mut self, props: Self::Properties) -> ShouldRender {
+        self.props = props;
+        true
+    }
+}
+
+impl Renderable<CandidatesModel> for CandidatesModel {
+    fn view(&self) -> Html<Self> {
+        let going_count = self.props.movie_night.going().len();
+        let view_candidate = |candidate: &Candidate| {
+            let moviedb_id = candidate.moviedb_id();
+            let anchor = candidate_anchor(moviedb_id);
+            let mut classes = vec!["candidate"];
+            if self.props.highlight.as_ref() == Some(&anchor) {
+                classes.push("highlighted");
+            }
+            if candidate.pinned {
+                classes.push("pinned");
+            }
+            if candidate.excluded {
+                classes.push("excluded");
+            }
+            if candidate.is_vetoed() {
+                classes.push("vetoed");
+            }
+            let proposed_by = candidate.proposed_by.iter()
+                .map(i32::to_string)
+                .collect::<Vec<String>>()
+                .join(", ");
+            let host_actions = if self.props.is_host {
+                html! {
+                    <>
+                        <button onclick=|_| Msg::TogglePinned(moviedb_id), disabled=self.task.is_some(),>
+                            { if candidate.pinned { "Unpin" } else { "Pin" } }
+                        </button>
+                        <button onclick=|_| Msg::ToggleExcluded(moviedb_id), disabled=self.task.is_some(),>
+                            { if candidate.excluded { "Include" } else { "Exclude" } }
+                        </button>
+                    </>
+                }
+            } else {
+                html! { <></> }
+            };
+
+            let vetoes = if candidate.is_vetoed() {
+                html! { <p class="vetoes",> { view_vetoes(&candidate.vetoes) } </p> }
+            } else if self.props.is_participant && self.props.vetoes_left > 0 {
+                html! {
+                    <button onclick=|_| Msg::Veto(moviedb_id), disabled=self.task.is_some(),>{ "Veto" }</button>
+                }
+            } else {
+                html! { <></> }
+            };
+
+            html! {
+                <li id=anchor, class=classes.join(" "),>
+                    <p> { &candidate.movie.title } </p>
+                    <p> { format!("Proposed by {}", proposed_by) } </p>
+                    <p> { format!("Wanted by {} of the {} participants going", candidate.proposed_by.len(), going_count) } </p>
+                    { vetoes }
+                    { host_actions }
+                    <button onclick=|_| Msg::Share(moviedb_id),>{ "Share" }</button>
+                </li>
+            }
+        };
+
+        let loading = if self.props.loading {
+            html! { <p class="loading",> { "Loading the selections..." } </p> }
+        } else {
+            html! { <></> }
+        };
+
+        let vetoes_left = if self.props.is_participant && self.props.movie_night.vetoes_per_participant > 0 {
+            html! { <p> { format!("Vetoes left: {} of {}", self.props.vetoes_left, self.props.movie_night.vetoes_per_participant) } </p> }
+        } else {
+            html! { <></> }
+        };
+
+        html! {
+            <div class="candidates",>
+                { loading }
+                { vetoes_left }
+                <ul> { for self.props.candidates.iter().map(view_candidate) } </ul>
+                { for self.error.iter().map(|error| html! { <p class="error",> { error } </p> }) }
+            </div>
+        }
+    }
+}
@@ -0,0 +1,130 @@
+use failure::Error;
+use yew::{html, Callback, Component, ComponentLink, Html, Renderable, ShouldRender};
+use yew::services::fetch::FetchTask;
+
+use crate::components::movie_night::display_date;
+use crate::routes;
+use crate::services::froovie_service::{FroovieService, Invite, InviteForm};
+
+/// Lets the host of a movie night create invite links to share.
+pub struct InviteModel {
+    props: Props,
+    form: InviteForm,
+    froovie: FroovieService,
+    callback: Callback<Result<Invite, Error>>,
+    task: Option<FetchTask>,
+    /// The invites created since the movie night was opened.
+    invites: Vec<Invite>,
+    error: Option<String>,
+}
+
+#[derive(Clone, PartialEq, Default)]
+pub struct Props {
+    pub movie_night_id: i32,
+    /// The date of the movie night, the default expiry of its invites.
+    pub date: String,
+}
+
+pub enum Msg {
+    ExpiresAt(String),
+    MaxUses(String),
+    Create,
+    Created(Result<Invite, Error>),
+    Copy(String),
+}
+
+/// The path of the page joining a movie night with an invite.
+pub fn join_path(token: &str) -> String {
+    format!("/join/{}", token)
+}
+
+impl Component for InviteModel {
+    type Message = Msg;
+    type Properties = Props;
+
+    fn create(props: Self::Properties, mut link: ComponentLink<Self>) -> Self {
+        InviteModel {
+            form: InviteForm {
+                expires_at: props.date.clone(),
+                max_uses: 1,
+            },
+            props,
+            froovie: FroovieService::new(),
+            callback: link.send_back(Msg::Created),
+            task: None,
+            invites: vec![],
+            error: None,
+        }
+    }
+
+    fn update(&mut self, msg: Self::Message) -> ShouldRender {
+        match msg {
+            Msg::ExpiresAt(expires_at) => {
+                self.form.expires_at = expires_at;
+            }
+            Msg::MaxUses(max_uses) => {
+                // Anything but a number leaves the limit as it was.
+                if let Ok(max_uses) = max_uses.trim().parse() {
+                    self.form.max_uses = max_uses;
+                }
+            }
+            Msg::Create => {
+                if self.form.expires_at.is_empty() || self.form.max_uses == 0 {
+                    self.error = Some("An invite needs an expiry date and at least one use.".into());
+                    return true;
+                }
+                let task = self.froovie.create_invite(self.props.movie_night_id, &self.form, self.callback.clone());
+                self.task = Some(task);
+            }
+            Msg::Created(Ok(invite)) => {
+                self.task = None;
+                self.error = None;
+                self.invites.push(invite);
+            }
+            Msg::Created(Err(error)) => {
+                self.task = None;
+                self.error = Some(error.to_string());
+            }
+            Msg::Copy(token) => {
+                routes::copy_link(&routes::route(&join_path(&token)));
+                return false;
+            }
+        }
+        true
+    }
+
+    fn change(&mut self, props: Self::Properties) -> ShouldRender {
+        self.props = props;
+        true
+    }
+}
+
+impl Renderable<InviteModel> for InviteModel {
+    fn view(&self) -> Html<Self> {
+        let view_invite = |invite: &Invite| {
+            let token = invite.token.clone();
+            html! {
+                <li>
+                    <code> { join_path(&invite.token) } </code>
+                    { format!(" - {} uses, expires {}", invite.max_uses, display_date(&invite.expires_at)) }
+                    <button onclick=|_| Msg::Copy(token.clone()),>{ "Copy link" }</button>
+                </li>
+            }
+        };
+
+        html! {
+            <div class="invite",>
+                <h3> { "Invite people" } </h3>
+                <label> { "Expires" }
+                    <input type="datetime-local", value=self.form.expires_at.clone(), oninput=|e| Msg::ExpiresAt(e.value),/>
+                </label>
+                <label> { "Usage limit" }
+                    <input type="number", min="1", value=self.form.max_uses.to_string(), oninput=|e| Msg::MaxUses(e.value),/>
+                </label>
+                <button onclick=|_| Msg::Create, disabled=self.task.is_some(),>{ "Create an invite link" }</button>
+                <ul> { for self.invites.iter().map(view_invite) } </ul>
+                { for self.error.iter().map(|error| html! { <p class="error",> { error } </p> }) }
+            </div>
+        }
+    }
+}
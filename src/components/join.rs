@@ -0,0 +1,167 @@
+use failure::Error;
+use yew::agent::Bridged;
+use yew::{html, Bridge, Callback, Component, ComponentLink, Html, Renderable, ShouldRender};
+use yew::services::fetch::FetchTask;
+
+use crate::components::movie_night::{display_date, local_now};
+use crate::components::router_link::RouterLink;
+use crate::router::Request;
+use crate::routes::{self, AppRouter};
+use crate::services::froovie_service::{FroovieService, InviteDetails, MovieNight, SignUp, User};
+use crate::services::session;
+
+/// The page an invite link opens, mounted at `/join/:token`.
+/// It shows the movie night and lets the recipient join it, signing up first if they have no account.
+/// The recipient is the user who signed up in this browser, never the default user.
+pub struct JoinModel {
+    props: Props,
+    /// The name to sign up with.
+    name: String,
+    froovie: FroovieService,
+    sign_up_callback: Callback<Result<User, Error>>,
+    join_callback: Callback<Result<MovieNight, Error>>,
+    task: Option<FetchTask>,
+    error: Option<String>,
+    router: Box<dyn Bridge<AppRouter>>,
+}
+
+#[derive(Clone, PartialEq, Default)]
+pub struct Props {
+    pub token: String,
+    /// The invite, loaded by the router shell.
+    pub invite: InviteDetails,
+}
+
+pub enum Msg {
+    Name(String),
+    Join,
+    SignUp,
+    SignedUp(Result<User, Error>),
+    Joined(Result<MovieNight, Error>),
+    Ignore,
+}
+
+impl JoinModel {
+    fn join(&mut self, user_id: i32) {
+        let task = self.froovie.join_movie_night(&self.props.token, user_id, self.join_callback.clone());
+        self.task = Some(task);
+    }
+}
+
+impl Component for JoinModel {
+    type Message = Msg;
+    type Properties = Props;
+
+    fn create(props: Self::Properties, mut link: ComponentLink<Self>) -> Self {
+        JoinModel {
+            props,
+            name: String::new(),
+            froovie: FroovieService::new(),
+            sign_up_callback: link.send_back(Msg::SignedUp),
+            join_callback: link.send_back(Msg::Joined),
+            task: None,
+            error: None,
+            router: AppRouter::bridge(link.send_back(|_| Msg::Ignore)),
+        }
+    }
+
+    fn update(&mut self, msg: Self::Message) -> ShouldRender {
+        match msg {
+            Msg::Name(name) => {
+                self.name = name;
+            }
+            Msg::Join => {
+                match session::signed_in_user() {
+                    Some(user_id) => self.join(user_id),
+                    None => return false,
+                }
+            }
+            Msg::SignUp => {
+                if self.name.trim().is_empty() {
+                    self.error = Some("Pick a name to sign up.".into());
+                    return true;
+                }
+                let sign_up = SignUp { name: self.name.trim() };
+                let task = self.froovie.sign_up(&sign_up, self.sign_up_callback.clone());
+                self.task = Some(task);
+            }
+            Msg::SignedUp(Ok(user)) => {
+                // The new user takes part from now on, on the movie night and everywhere else.
+                session::sign_in(user.id);
+                self.join(user.id);
+            }
+            Msg::Joined(Ok(movie_night)) => {
+                self.task = None;
+                // The invite should not be found again with the back button.
+                let route = routes::route(&format!("/movie_nights/{}", movie_night.id));
+                self.router.send(Request::ReplaceRoute(route));
+            }
+            Msg::SignedUp(Err(error)) | Msg::Joined(Err(error)) => {
+                self.task = None;
+                self.error = Some(error.to_string());
+            }
+            Msg::Ignore => return false,
+        }
+        true
+    }
+
+    fn change(&mut self, props: Self::Properties) -> ShouldRender {
+        self.props = props;
+        true
+    }
+}
+
+impl Renderable<JoinModel> for JoinModel {
+    fn view(&self) -> Html<Self> {
+        let InviteDetails { ref invite, ref movie_night } = self.props.invite;
+        let movie_night_path = format!("/movie_nights/{}", movie_night.id);
+        let user = session::signed_in_user();
+        let sign_up = html! {
+            <>
+                <label> { "Name" }
+                    <input type="text", value=self.name.clone(), oninput=|e| Msg::Name(e.value),/>
+                </label>
+                <button onclick=|_| Msg::SignUp, disabled=self.task.is_some(),>{ "Sign up and join" }</button>
+            </>
+        };
+        let actions = if user.map_or(false, |user_id| movie_night.participants.contains(&user_id)) {
+            html! {
+                <p>
+                    { "You are already taking part. " }
+                    <RouterLink: route=routes::route(&movie_night_path), text="See the movie night",/>
+                </p>
+            }
+        } else if movie_night.cancelled {
+            html! { <p> { "This movie night is cancelled." } </p> }
+        } else if !invite.is_usable(&local_now()) {
+            html! { <p> { "This invite has expired or has been used up. Ask the host for a new one." } </p> }
+        } else if let Some(user_id) = user {
+            html! {
+                <>
+                    <button onclick=|_| Msg::Join, disabled=self.task.is_some(),>{ format!("Join as user {}", user_id) }</button>
+                    <p> { format!("Not user {}? Sign up, then join.", user_id) } </p>
+                    { sign_up }
+                </>
+            }
+        } else {
+            html! {
+                <>
+                    <p> { "Sign up to join." } </p>
+                    { sign_up }
+                </>
+            }
+        };
+
+        html! {
+            <div class="join",>
+                <h2> { format!("You are invited to {}", movie_night.title) } </h2>
+                <p> { format!("When: {}", display_date(&movie_night.date)) } </p>
+                <p> { format!("Where: {}", movie_night.location) } </p>
+                <p> { format!("Host: {}", movie_night.host_id) } </p>
+                <p> { format!("Participants: {}", movie_night.participants.len()) } </p>
+                { actions }
+                { for self.error.iter().map(|error| html! { <p class="error",> { error } </p> }) }
+            </div>
+        }
+    }
+}
@@ -1,2 +1,14 @@
 pub mod user_selection;
-pub mod search_movie;
\ No newline at end of file
+pub mod search_movie;
+pub mod router_link;
+pub mod nav;
+pub mod movie_night;
+pub mod movie_night_form;
+pub mod movie_night_list;
+pub mod candidates;
+pub mod vote;
+pub mod results;
+pub mod invite;
+pub mod join;
+pub mod watch_history;
+pub mod not_found;
\ No newline at end of file
@@ -0,0 +1,631 @@
+use std::time::Duration;
+
+use failure::Error;
+use yew::agent::Bridged;
+use yew::{html, Bridge, Callback, Component, ComponentLink, Html, Renderable, ShouldRender};
+use yew::services::fetch::FetchTask;
+use yew::services::{IntervalService, IntervalTask};
+
+use crate::candidates::{self, Candidate};
+use crate::components::candidates::CandidatesModel;
+use crate::components::invite::InviteModel;
+use crate::components::movie_night_form::MovieNightFormModel;
+use crate::components::results::ResultsModel;
+use crate::components::router_link::RouterLink;
+use crate::components::vote::VoteModel;
+use crate::ics;
+use crate::router::{Mount, Request, Route};
+use crate::routes::{self, AppRouter, RouteState};
+use crate::routing;
+use crate::voting;
+use crate::services::froovie_service::{
+    Attendance, Ballot, FroovieService, Movie, MovieNight, Rsvp, Selections, WatchedForm, WatchedMovie,
+};
+use crate::services::session;
+
+/// How often the movie night is reloaded while the pool can change under the user's eyes.
+const POLL_INTERVAL: Duration = Duration::from_secs(5);
+
+/// A movie night, mounted at `/movie_nights/:id`.
+/// It routes the rest of the path to its own sub-views.
+pub struct MovieNightModel {
+    props: Props,
+    /// The movie night as last handed down by the router shell.
+    /// The displayed one can be more recent, after local changes and polls.
+    shell_movie_night: MovieNight,
+    view: View,
+    /// Fragment of the url, naming the item to highlight.
+    fragment: Option<String>,
+    link: ComponentLink<MovieNightModel>,
+    #[allow(dead_code)]
+    router: Box<dyn Bridge<AppRouter>>,
+    froovie: FroovieService,
+    callback: Callback<Result<MovieNight, Error>>,
+    task: Option<FetchTask>,
+    /// The selections of the participants loaded so far, feeding the candidate pool.
+    selections: Vec<(i32, Selections)>,
+    selection_tasks: Vec<FetchTask>,
+    /// How many selection requests finished, whether they succeeded or failed.
+    selections_finished: usize,
+    /// The ballots cast so far, as far as they could be loaded.
+    ballots: Vec<Ballot>,
+    ballots_loading: bool,
+    ballots_task: Option<FetchTask>,
+    interval: IntervalService,
+    /// Reloads the movie night, so vetoes and the host's choices show up live.
+    poll: Option<IntervalTask>,
+    poll_task: Option<FetchTask>,
+    watched_callback: Callback<Result<WatchedMovie, Error>>,
+    /// Whether marking the movie night as watched removes the movie from the attendees' selections.
+    remove_from_selections: bool,
+    error: Option<String>,
+}
+
+#[derive(Clone, PartialEq, Default)]
+pub struct Props {
+    pub id: String,
+    pub mount: Mount,
+    /// The movie night, loaded by the router shell.
+    pub movie_night: MovieNight,
+}
+
+/// Formats a movie night date, stored as `YYYY-MM-DDTHH:MM`, for display.
+pub fn display_date(date: &str) -> String {
+    date.replacen('T', " ", 1)
+}
+
+/// The local date and time, formatted like movie night dates so they can be compared.
+pub fn local_now() -> String {
+    let now = js! {
+        var now = new Date();
+        now.setMinutes(now.getMinutes() - now.getTimezoneOffset());
+        return now.toISOString().slice(0, 16);
+    };
+    now.into_string().unwrap_or_default()
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum View {
+    Overview,
+    Edit,
+    Candidates,
+    Vote,
+    Results,
+    NotFound(String),
+}
+
+impl View {
+    fn from_segments(segments: &[String]) -> View {
+        match segments.first().map(String::as_str) {
+            None => View::Overview,
+            Some("edit") => View::Edit,
+            Some("candidates") => View::Candidates,
+            Some("vote") => View::Vote,
+            Some("results") => View::Results,
+            Some(_) => View::NotFound(segments.join("/")),
+        }
+    }
+
+    fn title(&self) -> &str {
+        match self {
+            View::Overview => "Overview",
+            View::Edit => "Edit",
+            View::Candidates => "Candidates",
+            View::Vote => "Vote",
+            View::Results => "Results",
+            View::NotFound(_) => "Page not found",
+        }
+    }
+}
+
+pub enum Msg {
+    HandleRoute(Route<RouteState>),
+    Cancel,
+    Answer(Rsvp),
+    ExportCalendar,
+    ToggleRemoveFromSelections,
+    MarkWatched,
+    Watched(Result<WatchedMovie, Error>),
+    /// The movie night, once cancelled or answered.
+    Changed(Result<MovieNight, Error>),
+    SelectionsLoaded(i32, Result<Selections, Error>),
+    Updated(MovieNight),
+    BallotsLoaded(Result<Vec<Ballot>, Error>),
+    Voted(Ballot),
+    Poll,
+    Polled(Result<MovieNight, Error>),
+}
+
+impl Component for MovieNightModel {
+    type Message = Msg;
+    type Properties = Props;
+
+    fn create(props: Self::Properties, mut link: ComponentLink<Self>) -> Self {
+        let mut router = AppRouter::bridge(link.send_back(Msg::HandleRoute));
+        router.send(Request::GetCurrentRoute);
+
+        let callback = link.send_back(Msg::Changed);
+        let watched_callback = link.send_back(Msg::Watched);
+        let mut movie_night = MovieNightModel {
+            shell_movie_night: props.movie_night.clone(),
+            props,
+            view: View::Overview,
+            fragment: None,
+            link,
+            router,
+            froovie: FroovieService::new(),
+            callback,
+            task: None,
+            selections: vec![],
+            selection_tasks: vec![],
+            selections_finished: 0,
+            ballots: vec![],
+            ballots_loading: false,
+            ballots_task: None,
+            interval: IntervalService::new(),
+            poll: None,
+            poll_task: None,
+            watched_callback,
+            remove_from_selections: true,
+            error: None,
+        };
+        movie_night.load_selections();
+        movie_night.load_ballots();
+        movie_night
+    }
+
+    fn update(&mut self, msg: Self::Message) -> ShouldRender {
+        match msg {
+            Msg::HandleRoute(route) => {
+                // Routes outside of the mount point are about to unmount this component.
+                match self.props.mount.rest(&route) {
+                    Some(rest) => {
+                        let view = View::from_segments(rest);
+                        let changed = view != self.view || route.fragment != self.fragment;
+                        // Other participants may have voted since the ballots were loaded.
+                        if view == View::Results && view != self.view {
+                            self.load_ballots();
+                        }
+                        self.view = view;
+                        self.fragment = route.fragment.clone();
+                        self.update_poll();
+                        self.update_title();
+                        changed
+                    }
+                    None => false,
+                }
+            }
+            Msg::Cancel => {
+                let task = self.froovie.cancel_movie_night(self.props.movie_night.id, self.callback.clone());
+                self.task = Some(task);
+                true
+            }
+            Msg::Answer(rsvp) => {
+                let attendance = Attendance {
+                    participant: session::current_user_id(),
+                    rsvp,
+                };
+                let task = self.froovie.answer_rsvp(self.props.movie_night.id, &attendance, self.callback.clone());
+                self.task = Some(task);
+                true
+            }
+            Msg::ExportCalendar => {
+                let movie_night = &self.props.movie_night;
+                let url = routes::absolute_url(&self.props.mount.route(""));
+                let event = ics::event(movie_night, self.chosen_movie().as_ref(), &ics::stamp(), &url);
+                match event {
+                    Some(event) => {
+                        let file_name = format!("movie-night-{}.ics", movie_night.id);
+                        ics::download(&file_name, &ics::calendar(vec![event]));
+                        false
+                    }
+                    None => {
+                        self.error = Some(format!("Invalid date: '{}'", movie_night.date));
+                        true
+                    }
+                }
+            }
+            Msg::ToggleRemoveFromSelections => {
+                self.remove_from_selections = !self.remove_from_selections;
+                true
+            }
+            Msg::MarkWatched => {
+                let movie = match self.chosen_movie() {
+                    Some(movie) => movie,
+                    None => return false,
+                };
+                let movie_night = &self.props.movie_night;
+                let form = WatchedForm {
+                    moviedb_id: movie.moviedb_id,
+                    date: movie_night.date.clone(),
+                    attendees: movie_night.going(),
+                    remove_from_selections: self.remove_from_selections,
+                };
+                let task = self.froovie.mark_watched(movie_night.id, &form, self.watched_callback.clone());
+                self.task = Some(task);
+                true
+            }
+            Msg::Watched(Ok(_)) => {
+                self.task = None;
+                self.props.movie_night.watched = true;
+                if self.remove_from_selections {
+                    self.load_selections();
+                }
+                true
+            }
+            Msg::Watched(Err(error)) => {
+                self.task = None;
+                self.error = Some(error.to_string());
+                true
+            }
+            Msg::Changed(Ok(movie_night)) => {
+                self.task = None;
+                self.props.movie_night = movie_night;
+                true
+            }
+            Msg::Changed(Err(error)) => {
+                self.task = None;
+                self.error = Some(error.to_string());
+                true
+            }
+            Msg::SelectionsLoaded(participant, Ok(selections)) => {
+                self.selections.push((participant, selections));
+                self.selection_finished();
+                true
+            }
+            Msg::SelectionsLoaded(participant, Err(error)) => {
+                // The pool is shown without the movies of this participant.
+                self.error = Some(format!("Could not load the selection of participant {}: {}", participant, error));
+                self.selection_finished();
+                true
+            }
+            Msg::Updated(movie_night) => {
+                self.set_movie_night(movie_night);
+                true
+            }
+            Msg::Poll => {
+                // Skip a beat rather than pile up requests on a slow connection.
+                if self.poll_task.is_none() {
+                    let callback = self.link.send_back(Msg::Polled);
+                    self.poll_task = Some(self.froovie.get_movie_night(&self.props.movie_night.id.to_string(), callback));
+                }
+                false
+            }
+            Msg::Polled(Ok(movie_night)) => {
+                self.poll_task = None;
+                if movie_night == self.props.movie_night {
+                    return false;
+                }
+                self.set_movie_night(movie_night);
+                true
+            }
+            Msg::Polled(Err(_)) => {
+                // The next poll tries again.
+                self.poll_task = None;
+                false
+            }
+            Msg::BallotsLoaded(Ok(ballots)) => {
+                self.ballots_task = None;
+                self.ballots_loading = false;
+                self.ballots = ballots;
+                true
+            }
+            Msg::BallotsLoaded(Err(error)) => {
+                // The results are shown from the ballots loaded before, if any.
+                self.ballots_task = None;
+                self.ballots_loading = false;
+                self.error = Some(format!("Could not load the ballots: {}", error));
+                true
+            }
+            Msg::Voted(ballot) => {
+                self.ballots.retain(|other| other.voter != ballot.voter);
+                self.ballots.push(ballot);
+                true
+            }
+        }
+    }
+
+    fn change(&mut self, props: Self::Properties) -> ShouldRender {
+        self.props.id = props.id;
+        self.props.mount = props.mount;
+        // The shell keeps handing down the copy it loaded, which would revert the local changes.
+        // Only a movie night it loaded again replaces the displayed one.
+        if props.movie_night != self.shell_movie_night {
+            self.shell_movie_night = props.movie_night.clone();
+            self.set_movie_night(props.movie_night);
+        }
+        self.update_title();
+        true
+    }
+}
+
+impl Renderable<MovieNightModel> for MovieNightModel {
+    fn view(&self) -> Html<Self> {
+        let mount = &self.props.mount;
+        html! {
+            <div>
+                <nav class="movie-night-menu",>
+                    <RouterLink: route=mount.route(""), text="Overview", exact=true,/>
+                    <RouterLink: route=mount.route("edit"), text="Edit",/>
+                    <RouterLink: route=mount.route("candidates"), text="Candidates",/>
+                    <RouterLink: route=mount.route("vote"), text="Vote",/>
+                    <RouterLink: route=mount.route("results"), text="Results",/>
+                </nav>
+                { self.view_sub_view() }
+                { for self.error.iter().map(|error| html! { <p class="error",> { error } </p> }) }
+            </div>
+        }
+    }
+}
+
+impl MovieNightModel {
+    fn update_title(&self) {
+        let movie_night = &self.props.movie_night;
+        routes::set_title(&format!("{} - {} ({})", self.view.title(), movie_night.title, display_date(&movie_night.date)));
+    }
+
+    /// Displays another version of the movie night, reloading the selections if the participants changed.
+    fn set_movie_night(&mut self, movie_night: MovieNight) {
+        let participants_changed = movie_night.participants != self.props.movie_night.participants;
+        self.props.movie_night = movie_night;
+        if participants_changed {
+            self.load_selections();
+        }
+    }
+
+    /// Polls the movie night while a view showing the candidate pool is displayed.
+    fn update_poll(&mut self) {
+        match self.view {
+            View::Candidates | View::Vote => {
+                if self.poll.is_none() {
+                    let callback = self.link.send_back(|_| Msg::Poll);
+                    self.poll = Some(self.interval.spawn(POLL_INTERVAL, callback));
+                }
+            }
+            _ => {
+                self.poll = None;
+                self.poll_task = None;
+            }
+        }
+    }
+
+    /// Loads the selections of every participant, replacing those already loaded.
+    fn load_selections(&mut self) {
+        self.selections.clear();
+        self.selections_finished = 0;
+        self.selection_tasks = self.props.movie_night.participants.iter()
+            .map(|&participant| {
+                let callback = self.link.send_back(move |selections| Msg::SelectionsLoaded(participant, selections));
+                self.froovie.get_user_selection(&participant.to_string(), callback)
+            })
+            .collect();
+    }
+
+    /// Counts a finished selection request. Once the pool is complete,
+    /// scrolls to the candidate named by the fragment, which the shell could not find before.
+    fn selection_finished(&mut self) {
+        self.selections_finished += 1;
+        if !self.selections_loading() && self.view == View::Candidates && self.fragment.is_some() {
+            routing::scroll_to_fragment(self.fragment.as_ref().map(String::as_str));
+        }
+    }
+
+    fn load_ballots(&mut self) {
+        let callback = self.link.send_back(Msg::BallotsLoaded);
+        self.ballots_loading = true;
+        self.ballots_task = Some(self.froovie.list_ballots(self.props.movie_night.id, callback));
+    }
+
+    fn candidates(&self) -> Vec<Candidate> {
+        candidates::pool(&self.props.movie_night, &self.selections)
+    }
+
+    /// Whether the selections of some participants are still loading, leaving the pool incomplete.
+    /// Selections that failed to load count as finished, the pool goes without them.
+    fn selections_loading(&self) -> bool {
+        self.selections_finished < self.selection_tasks.len()
+    }
+
+    /// The candidates that can be voted for.
+    fn eligible_candidates(&self) -> Vec<Candidate> {
+        self.candidates().into_iter().filter(Candidate::is_eligible).collect()
+    }
+
+    /// The movie the vote chose, once there are ballots.
+    fn chosen_movie(&self) -> Option<Movie> {
+        if self.ballots_loading {
+            return None;
+        }
+        let candidates = self.eligible_candidates();
+        let moviedb_ids: Vec<i32> = candidates.iter().map(Candidate::moviedb_id).collect();
+        let movie_night = &self.props.movie_night;
+        let winner = voting::winner(movie_night.id, movie_night.voting_method, &moviedb_ids, &self.ballots)?;
+        candidates.into_iter()
+            .find(|candidate| candidate.moviedb_id() == winner)
+            .map(|candidate| candidate.movie)
+    }
+
+    fn ballot(&self) -> Option<Ballot> {
+        self.ballots.iter()
+            .find(|ballot| ballot.voter == session::current_user_id())
+            .cloned()
+    }
+
+    fn is_host(&self) -> bool {
+        self.props.movie_night.host_id == session::current_user_id()
+    }
+
+    fn is_participant(&self) -> bool {
+        self.props.movie_night.participants.contains(&session::current_user_id())
+    }
+
+    /// How many more candidates the current user can veto.
+    fn vetoes_left(&self) -> u32 {
+        let movie_night = &self.props.movie_night;
+        let user_id = session::current_user_id();
+        let used = movie_night.vetoes.iter()
+            .filter(|veto| veto.voter == Some(user_id))
+            .count() as u32;
+        movie_night.vetoes_per_participant.saturating_sub(used)
+    }
+
+    /// The buttons answering whether the current user is coming.
+    fn view_rsvp(&self) -> Html<Self> {
+        let answer = self.props.movie_night.rsvp(session::current_user_id());
+        let view_answer = |rsvp: &Rsvp| {
+            let rsvp = *rsvp;
+            let class = if answer == Some(rsvp) { "rsvp selected" } else { "rsvp" };
+            html! {
+                <button class=class, onclick=|_| Msg::Answer(rsvp), disabled=self.task.is_some(),>{ rsvp.label() }</button>
+            }
+        };
+
+        html! {
+            <div class="rsvps",>
+                { "Are you coming? " }
+                { for Rsvp::ALL.iter().map(view_answer) }
+            </div>
+        }
+    }
+
+    /// Who is coming, for the host.
+    fn view_headcount(&self) -> Html<Self> {
+        let movie_night = &self.props.movie_night;
+        let answered = |rsvp: Option<Rsvp>| movie_night.participants.iter()
+            .cloned()
+            .filter(|participant| movie_night.rsvp(*participant) == rsvp)
+            .collect::<Vec<i32>>();
+        let view_count = |(label, participants): (&str, Vec<i32>)| {
+            let ids = participants.iter().map(i32::to_string).collect::<Vec<String>>().join(", ");
+            html! {
+                <li> { format!("{}: {} ({})", label, participants.len(), ids) } </li>
+            }
+        };
+        let counts = vec![
+            (Rsvp::Going.label(), answered(Some(Rsvp::Going))),
+            (Rsvp::Maybe.label(), answered(Some(Rsvp::Maybe))),
+            (Rsvp::NotGoing.label(), answered(Some(Rsvp::NotGoing))),
+            ("No answer", answered(None)),
+        ];
+
+        html! {
+            <div class="headcount",>
+                <h3> { format!("Headcount: {} of {} participants going", movie_night.going().len(), movie_night.participants.len()) } </h3>
+                <ul> { for counts.into_iter().map(view_count) } </ul>
+            </div>
+        }
+    }
+
+    /// Lets the host record the chosen movie in the watch history, once the movie night took place.
+    fn view_mark_watched(&self) -> Html<Self> {
+        let movie_night = &self.props.movie_night;
+        if movie_night.watched {
+            return html! {
+                <p class="watched",>
+                    { "We watched it! " }
+                    <RouterLink: route=routes::route("/history"), text="See the watch history",/>
+                </p>
+            };
+        }
+        let movie = match self.chosen_movie() {
+            Some(ref movie) if movie_night.date <= local_now() => movie.clone(),
+            _ => return html! { <></> },
+        };
+
+        html! {
+            <div class="mark-watched",>
+                <label>
+                    <input type="checkbox", checked=self.remove_from_selections, onclick=|_| Msg::ToggleRemoveFromSelections,/>
+                    { "Remove it from the selections of those who came" }
+                </label>
+                <button onclick=|_| Msg::MarkWatched, disabled=self.task.is_some(),>
+                    { format!("We watched {}", movie.title) }
+                </button>
+            </div>
+        }
+    }
+
+    fn view_overview(&self) -> Html<Self> {
+        let movie_night = &self.props.movie_night;
+        let participants = movie_night.participants.iter()
+            .map(i32::to_string)
+            .collect::<Vec<String>>()
+            .join(", ");
+        let cancelled = if movie_night.cancelled {
+            html! { <p class="cancelled",> { "This movie night is cancelled." } </p> }
+        } else {
+            html! { <></> }
+        };
+        let rsvp = if self.is_participant() && !movie_night.cancelled {
+            self.view_rsvp()
+        } else {
+            html! { <></> }
+        };
+        let host_actions = if self.is_host() && !movie_night.cancelled {
+            html! {
+                <>
+                    { self.view_headcount() }
+                    { self.view_mark_watched() }
+                    <InviteModel: movie_night_id=movie_night.id, date=movie_night.date.clone(),/>
+                    <button onclick=|_| Msg::Cancel, disabled=self.task.is_some(),>{ "Cancel the movie night" }</button>
+                </>
+            }
+        } else {
+            html! { <></> }
+        };
+
+        html! {
+            <div class="movie-night",>
+                <h2> { &movie_night.title } </h2>
+                { cancelled }
+                <p> { format!("When: {}", display_date(&movie_night.date)) } </p>
+                <p> { format!("Where: {}", movie_night.location) } </p>
+                <p> { format!("Host: {}", movie_night.host_id) } </p>
+                <p> { format!("Participants: {}", participants) } </p>
+                { rsvp }
+                <button onclick=|_| Msg::ExportCalendar,>{ "Add to my calendar" }</button>
+                { host_actions }
+            </div>
+        }
+    }
+
+    fn view_sub_view(&self) -> Html<Self> {
+        match self.view {
+            View::Overview => self.view_overview(),
+            View::Edit if self.is_host() => html! {
+                <MovieNightFormModel: mount=self.props.mount.nest("edit"), movie_night=Some(self.props.movie_night.clone()),/>
+            },
+            View::Edit => html! {
+                <p> { "Only the host can edit this movie night." } </p>
+            },
+            View::Candidates => html! {
+                <CandidatesModel: movie_night=self.props.movie_night.clone(),
+                    candidates=self.candidates(),
+                    loading=self.selections_loading(),
+                    is_host=self.is_host(),
+                    is_participant=self.is_participant(),
+                    vetoes_left=self.vetoes_left(),
+                    mount=self.props.mount.nest("candidates"),
+                    highlight=self.fragment.clone(),
+                    onupdate=|movie_night| Msg::Updated(movie_night),/>
+            },
+            View::Vote => html! {
+                <VoteModel: movie_night_id=self.props.movie_night.id,
+                    voting_method=self.props.movie_night.voting_method,
+                    candidates=self.eligible_candidates(),
+                    ballot=self.ballot(),
+                    onvote=|ballot| Msg::Voted(ballot),/>
+            },
+            View::Results => html! {
+                <ResultsModel: movie_night_id=self.props.movie_night.id,
+                    voting_method=self.props.movie_night.voting_method,
+                    candidates=self.eligible_candidates(),
+                    ballots=self.ballots.clone(),
+                    loading=self.ballots_loading || self.selections_loading(),/>
+            },
+            View::NotFound(ref path) => html! {
+                <p> { format!("Invalid path: '{}'", path) } </p>
+            },
+        }
+    }
+}
@@ -0,0 +1,245 @@
+use failure::Error;
+use yew::agent::Bridged;
+use yew::{html, Bridge, Callback, Component, ComponentLink, Html, Renderable, ShouldRender};
+use yew::services::fetch::FetchTask;
+
+use crate::router::{Guard, GuardAction, GuardCondition, Mount, Request};
+use crate::routes::{self, AppRouter};
+use crate::services::froovie_service::{FroovieService, MovieNight, MovieNightForm, VotingMethod};
+use crate::services::session;
+
+/// Form creating a movie night, or editing the one given as props.
+/// Leaving it with unsaved changes asks for confirmation.
+pub struct MovieNightFormModel {
+    props: Props,
+    form: MovieNightForm,
+    /// The participants as typed, a comma separated list of user ids.
+    participants: String,
+    /// Whether there are unsaved changes, guarded against leaving the form.
+    dirty: bool,
+    froovie: FroovieService,
+    callback: Callback<Result<MovieNight, Error>>,
+    task: Option<FetchTask>,
+    error: Option<String>,
+    router: Box<dyn Bridge<AppRouter>>,
+}
+
+#[derive(Clone, PartialEq, Default)]
+pub struct Props {
+    /// Where the form is displayed.
+    pub mount: Mount,
+    /// The movie night to edit, or `None` to create one.
+    pub movie_night: Option<MovieNight>,
+}
+
+pub enum Msg {
+    Title(String),
+    Date(String),
+    Location(String),
+    Participants(String),
+    VotingMethod(VotingMethod),
+    Vetoes(String),
+    ToggleAnonymousVetoes,
+    Submit,
+    Cancel,
+    Saved(Result<MovieNight, Error>),
+    Ignore,
+}
+
+impl MovieNightFormModel {
+    fn form_for(movie_night: &Option<MovieNight>) -> (MovieNightForm, String) {
+        match movie_night {
+            Some(movie_night) => {
+                let form = MovieNightForm {
+                    title: movie_night.title.clone(),
+                    date: movie_night.date.clone(),
+                    location: movie_night.location.clone(),
+                    host_id: movie_night.host_id,
+                    participants: movie_night.participants.clone(),
+                    voting_method: movie_night.voting_method,
+                    vetoes_per_participant: movie_night.vetoes_per_participant,
+                    anonymous_vetoes: movie_night.anonymous_vetoes,
+                };
+                let participants = movie_night.participants.iter()
+                    .map(i32::to_string)
+                    .collect::<Vec<String>>()
+                    .join(", ");
+                (form, participants)
+            }
+            None => {
+                let form = MovieNightForm {
+                    host_id: session::current_user_id(),
+                    ..MovieNightForm::default()
+                };
+                (form, String::new())
+            }
+        }
+    }
+
+    /// Records an edit, guarding the form against being left once it has changes.
+    fn edited(&mut self) {
+        if !self.dirty {
+            self.dirty = true;
+            self.router.send(Request::AddGuard(Guard {
+                condition: GuardCondition::Leaving(self.props.mount.base.clone()),
+                action: GuardAction::Confirm("Leave without saving this movie night?".into()),
+            }));
+        }
+    }
+}
+
+impl Component for MovieNightFormModel {
+    type Message = Msg;
+    type Properties = Props;
+
+    fn create(props: Self::Properties, mut link: ComponentLink<Self>) -> Self {
+        let (form, participants) = Self::form_for(&props.movie_night);
+        MovieNightFormModel {
+            props,
+            form,
+            participants,
+            dirty: false,
+            froovie: FroovieService::new(),
+            callback: link.send_back(Msg::Saved),
+            task: None,
+            error: None,
+            router: AppRouter::bridge(link.send_back(|_| Msg::Ignore)),
+        }
+    }
+
+    fn update(&mut self, msg: Self::Message) -> ShouldRender {
+        match msg {
+            Msg::Title(title) => {
+                self.form.title = title;
+                self.edited();
+            }
+            Msg::Date(date) => {
+                self.form.date = date;
+                self.edited();
+            }
+            Msg::Location(location) => {
+                self.form.location = location;
+                self.edited();
+            }
+            Msg::Participants(participants) => {
+                self.participants = participants;
+                self.edited();
+            }
+            Msg::VotingMethod(voting_method) => {
+                self.form.voting_method = voting_method;
+                self.edited();
+            }
+            Msg::Vetoes(vetoes) => {
+                // Anything but a number leaves the count as it was.
+                if let Ok(vetoes) = vetoes.trim().parse() {
+                    self.form.vetoes_per_participant = vetoes;
+                }
+                self.edited();
+            }
+            Msg::ToggleAnonymousVetoes => {
+                self.form.anonymous_vetoes = !self.form.anonymous_vetoes;
+                self.edited();
+            }
+            Msg::Submit => {
+                if self.form.title.trim().is_empty() || self.form.date.is_empty() {
+                    self.error = Some("A movie night needs a title and a date.".into());
+                    return true;
+                }
+                let mut participants: Vec<i32> = self.participants.split(',')
+                    .filter_map(|id| id.trim().parse().ok())
+                    .collect();
+                if !participants.contains(&self.form.host_id) {
+                    participants.insert(0, self.form.host_id);
+                }
+                self.form.participants = participants;
+
+                let task = match self.props.movie_night {
+                    Some(ref movie_night) => self.froovie.update_movie_night(movie_night.id, &self.form, self.callback.clone()),
+                    None => self.froovie.create_movie_night(&self.form, self.callback.clone()),
+                };
+                self.task = Some(task);
+            }
+            Msg::Cancel => {
+                self.router.send(Request::Back);
+                return false;
+            }
+            Msg::Saved(Ok(movie_night)) => {
+                self.task = None;
+                self.dirty = false;
+                self.router.send(Request::ClearGuards);
+                // The form should not be found again with the back button.
+                let route = routes::route(&format!("/movie_nights/{}", movie_night.id));
+                self.router.send(Request::ReplaceRoute(route));
+                if self.props.movie_night.is_some() {
+                    // The movie night is still displayed, have it reloaded.
+                    self.router.send(Request::RefreshRoute);
+                }
+            }
+            Msg::Saved(Err(error)) => {
+                self.task = None;
+                self.error = Some(error.to_string());
+            }
+            Msg::Ignore => return false,
+        }
+        true
+    }
+
+    fn change(&mut self, props: Self::Properties) -> ShouldRender {
+        if props.movie_night != self.props.movie_night && !self.dirty {
+            let (form, participants) = Self::form_for(&props.movie_night);
+            self.form = form;
+            self.participants = participants;
+        }
+        self.props = props;
+        true
+    }
+}
+
+impl Renderable<MovieNightFormModel> for MovieNightFormModel {
+    fn view(&self) -> Html<Self> {
+        let submit_text = if self.props.movie_night.is_some() { "Save" } else { "Create" };
+        let view_voting_method = |voting_method: &VotingMethod| {
+            let voting_method = *voting_method;
+            html! {
+                <label>
+                    <input type="radio", name="voting-method",
+                        checked=self.form.voting_method == voting_method,
+                        onclick=|_| Msg::VotingMethod(voting_method),/>
+                    { voting_method.description() }
+                </label>
+            }
+        };
+        html! {
+            <div class="movie-night-form",>
+                <label> { "Title" }
+                    <input type="text", value=self.form.title.clone(), oninput=|e| Msg::Title(e.value),/>
+                </label>
+                <label> { "Date" }
+                    <input type="datetime-local", value=self.form.date.clone(), oninput=|e| Msg::Date(e.value),/>
+                </label>
+                <label> { "Location" }
+                    <input type="text", value=self.form.location.clone(), oninput=|e| Msg::Location(e.value),/>
+                </label>
+                <label> { "Participants (user ids, separated by commas)" }
+                    <input type="text", value=self.participants.clone(), oninput=|e| Msg::Participants(e.value),/>
+                </label>
+                <fieldset>
+                    <legend> { "Voting" } </legend>
+                    { for VotingMethod::ALL.iter().map(view_voting_method) }
+                </fieldset>
+                <label> { "Vetoes per participant" }
+                    <input type="number", min="0",
+                        value=self.form.vetoes_per_participant.to_string(),
+                        oninput=|e| Msg::Vetoes(e.value),/>
+                </label>
+                <label>
+                    <input type="checkbox", checked=self.form.anonymous_vetoes, onclick=|_| Msg::ToggleAnonymousVetoes,/>
+                    { "Anonymous vetoes" }
+                </label>
+                <button onclick=|_| Msg::Submit, disabled=self.task.is_some(),>{ submit_text }</button>
+                <button onclick=|_| Msg::Cancel,>{ "Cancel" }</button>
+                { for self.error.iter().map(|error| html! { <p class="error",> { error } </p> }) }
+            </div>
+        }
+    }
+}
@@ -0,0 +1,85 @@
+use yew::{html, Component, ComponentLink, Html, Renderable, ShouldRender};
+
+use crate::components::movie_night::{display_date, local_now};
+use crate::components::router_link::RouterLink;
+use crate::ics;
+use crate::routes;
+use crate::services::froovie_service::{self, MovieNight};
+use crate::services::session;
+
+/// The movie nights, loaded by the router shell, with a link to plan a new one
+/// and the calendar of the upcoming ones.
+pub struct MovieNightListModel {
+    movie_nights: Vec<MovieNight>,
+}
+
+#[derive(Clone, PartialEq, Default)]
+pub struct Props {
+    pub movie_nights: Vec<MovieNight>,
+}
+
+pub enum Msg {
+    DownloadUpcoming,
+}
+
+impl Component for MovieNightListModel {
+    type Message = Msg;
+    type Properties = Props;
+
+    fn create(props: Self::Properties, _: ComponentLink<Self>) -> Self {
+        MovieNightListModel {
+            movie_nights: props.movie_nights,
+        }
+    }
+
+    fn update(&mut self, msg: Self::Message) -> ShouldRender {
+        match msg {
+            Msg::DownloadUpcoming => {
+                let url = |movie_night: &MovieNight| {
+                    routes::absolute_url(&routes::route(&format!("/movie_nights/{}", movie_night.id)))
+                };
+                let calendar = ics::upcoming(&self.movie_nights, session::current_user_id(), &local_now(), &ics::stamp(), url);
+                ics::download("movie-nights.ics", &calendar);
+            }
+        }
+        false
+    }
+
+    fn change(&mut self, props: Self::Properties) -> ShouldRender {
+        self.movie_nights = props.movie_nights;
+        true
+    }
+}
+
+impl Renderable<MovieNightListModel> for MovieNightListModel {
+    fn view(&self) -> Html<Self> {
+        let view_movie_night = |movie_night: &MovieNight| {
+            let text = if movie_night.cancelled {
+                format!("{} ({}, cancelled)", movie_night.title, display_date(&movie_night.date))
+            } else {
+                format!("{} ({})", movie_night.title, display_date(&movie_night.date))
+            };
+            html! {
+                <li>
+                    <RouterLink: route=routes::route(&format!("/movie_nights/{}", movie_night.id)), text=text,/>
+                </li>
+            }
+        };
+
+        let feed_url = froovie_service::movie_nights_feed_url(session::current_user_id());
+
+        html! {
+            <div>
+                <RouterLink: route=routes::route("/movie_nights/new"), text="Plan a movie night", exact=true,/>
+                <ul> { for self.movie_nights.iter().map(view_movie_night) } </ul>
+                <div class="calendar",>
+                    <button onclick=|_| Msg::DownloadUpcoming,>{ "Download my upcoming movie nights" }</button>
+                    <p>
+                        { "Or subscribe to them in your calendar app: " }
+                        <a href=feed_url.clone(),> { &feed_url } </a>
+                    </p>
+                </div>
+            </div>
+        }
+    }
+}
@@ -0,0 +1,33 @@
+//! The navigation menu shared by every section of the app.
+
+use yew::{html, Component, ComponentLink, Html, Renderable, ShouldRender};
+
+use crate::components::router_link::RouterLink;
+use crate::routes::{self, SECTIONS};
+
+pub struct Nav;
+
+impl Component for Nav {
+    type Message = ();
+    type Properties = ();
+
+    fn create(_: Self::Properties, _: ComponentLink<Self>) -> Self {
+        Nav
+    }
+
+    fn update(&mut self, _: Self::Message) -> ShouldRender {
+        false
+    }
+}
+
+impl Renderable<Nav> for Nav {
+    fn view(&self) -> Html<Self> {
+        html! {
+            <nav class="menu",>
+                { for SECTIONS.iter().map(|(path, name)| html! {
+                    <RouterLink: route=routes::route(path), text=name.to_string(),/>
+                }) }
+            </nav>
+        }
+    }
+}
@@ -0,0 +1,61 @@
+use yew::{html, Component, ComponentLink, Html, Renderable, ShouldRender};
+
+use crate::components::router_link::RouterLink;
+use crate::routes::{self, SECTIONS};
+
+/// Shown for unknown paths, with a link to the closest known route and to the main sections.
+pub struct NotFoundModel {
+    path: String,
+}
+
+#[derive(Clone, PartialEq, Default)]
+pub struct Props {
+    pub path: String,
+}
+
+impl Component for NotFoundModel {
+    type Message = ();
+    type Properties = Props;
+
+    fn create(props: Self::Properties, _: ComponentLink<Self>) -> Self {
+        NotFoundModel { path: props.path }
+    }
+
+    fn update(&mut self, _: Self::Message) -> ShouldRender {
+        false
+    }
+
+    fn change(&mut self, props: Self::Properties) -> ShouldRender {
+        self.path = props.path;
+        true
+    }
+}
+
+impl Renderable<NotFoundModel> for NotFoundModel {
+    fn view(&self) -> Html<Self> {
+        let suggestion = match routes::closest_section(&self.path) {
+            Some((path, name)) => html! {
+                <p>
+                    { "Did you mean " }
+                    <RouterLink: route=routes::route(path), text=name.to_string(),/>
+                    { "?" }
+                </p>
+            },
+            None => html! { <></> },
+        };
+
+        html! {
+            <div class="not-found",>
+                <h2> { "Page not found" } </h2>
+                <p> { format!("There is nothing at '{}'.", self.path) } </p>
+                { suggestion }
+                <p> { "You can go to one of these sections instead:" } </p>
+                <ul>
+                    { for SECTIONS.iter().map(|(path, name)| html! {
+                        <li> <RouterLink: route=routes::route(path), text=name.to_string(),/> </li>
+                    }) }
+                </ul>
+            </div>
+        }
+    }
+}
@@ -0,0 +1,124 @@
+use yew::{html, Component, ComponentLink, Html, Renderable, ShouldRender};
+
+use crate::candidates::Candidate;
+use crate::services::froovie_service::{Ballot, VotingMethod};
+use crate::voting::{self, Outcome, Round, Tally};
+use crate::voting::tie_break::{self, TieBreak};
+
+/// The results of the vote on a movie night, round by round.
+pub struct ResultsModel {
+    props: Props,
+}
+
+#[derive(Clone, PartialEq, Default)]
+pub struct Props {
+    pub movie_night_id: i32,
+    pub voting_method: VotingMethod,
+    /// The candidates that can be voted for.
+    pub candidates: Vec<Candidate>,
+    pub ballots: Vec<Ballot>,
+    /// Whether the ballots are still loading.
+    pub loading: bool,
+}
+
+impl ResultsModel {
+    fn title(&self, moviedb_id: i32) -> String {
+        self.props.candidates.iter()
+            .find(|candidate| candidate.moviedb_id() == moviedb_id)
+            .map(|candidate| candidate.movie.title.clone())
+            .unwrap_or_else(|| format!("Movie {}", moviedb_id))
+    }
+
+    fn titles(&self, moviedb_ids: &[i32]) -> String {
+        moviedb_ids.iter()
+            .map(|moviedb_id| self.title(*moviedb_id))
+            .collect::<Vec<String>>()
+            .join(", ")
+    }
+
+    fn view_tie_break(&self, tie_break: &TieBreak) -> Html<Self> {
+        html! {
+            <div class="tie-break",>
+                <h4> { format!("Drawn winner: {}", self.title(tie_break.winner)) } </h4>
+                <p> { format!("Tied candidates, by moviedb id: {}", tie_break_candidates(tie_break)) } </p>
+                <p> { "Seed input: " } <code> { &tie_break.seed_input } </code> </p>
+                <p> { "Seed: " } <code> { format!("{:016x}", tie_break.seed) } </code> </p>
+                <p> { tie_break::PROCEDURE } </p>
+            </div>
+        }
+    }
+
+    fn view_round(&self, tally: &dyn Tally, (index, round): (usize, &Round)) -> Html<Self> {
+        let eliminated = if round.eliminated.is_empty() {
+            html! { <></> }
+        } else {
+            html! { <p> { format!("Eliminated: {}", self.titles(&round.eliminated)) } </p> }
+        };
+
+        html! {
+            <li class="round",>
+                <h4> { format!("Round {}", index + 1) } </h4>
+                <ul> { for round.counts.iter().map(|(moviedb_id, votes)| html! {
+                    <li> { format!("{}: {} {}", self.title(*moviedb_id), votes, tally.unit()) } </li>
+                }) } </ul>
+                <p> { format!("Exhausted ballots: {}", round.exhausted) } </p>
+                { eliminated }
+            </li>
+        }
+    }
+}
+
+fn tie_break_candidates(tie_break: &TieBreak) -> String {
+    tie_break.tied.iter()
+        .map(i32::to_string)
+        .collect::<Vec<String>>()
+        .join(", ")
+}
+
+impl Component for ResultsModel {
+    type Message = ();
+    type Properties = Props;
+
+    fn create(props: Self::Properties, _: ComponentLink<Self>) -> Self {
+        ResultsModel { props }
+    }
+
+    fn update(&mut self, _: Self::Message) -> ShouldRender {
+        false
+    }
+
+    fn change(&mut self, props: Self::Properties) -> ShouldRender {
+        self.props = props;
+        true
+    }
+}
+
+impl Renderable<ResultsModel> for ResultsModel {
+    fn view(&self) -> Html<Self> {
+        if self.props.loading {
+            return html! { <p class="loading",> { "Loading the ballots and candidates..." } </p> };
+        }
+
+        let candidates: Vec<i32> = self.props.candidates.iter().map(Candidate::moviedb_id).collect();
+        let tally = voting::tally_for(self.props.voting_method);
+        let results = tally.tally(&candidates, &self.props.ballots);
+        let outcome = match results.outcome {
+            Outcome::Winner(moviedb_id) => format!("Winner: {}", self.title(moviedb_id)),
+            Outcome::Tie(ref moviedb_ids) => format!("Tie between {}", self.titles(moviedb_ids)),
+            Outcome::NoVotes => "No votes yet".to_string(),
+        };
+        let tie_break = match results.outcome {
+            Outcome::Tie(ref moviedb_ids) => tie_break::draw(self.props.movie_night_id, &self.props.ballots, moviedb_ids),
+            _ => None,
+        };
+
+        html! {
+            <div class="results",>
+                <h3> { outcome } </h3>
+                { for tie_break.iter().map(|tie_break| self.view_tie_break(tie_break)) }
+                <p> { format!("{} ballots, counted with {}.", self.props.ballots.len(), tally.name()) } </p>
+                <ol> { for results.rounds.iter().enumerate().map(|round| self.view_round(tally.as_ref(), round)) } </ol>
+            </div>
+        }
+    }
+}
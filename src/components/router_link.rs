@@ -0,0 +1,101 @@
+//! Anchor that navigates through the `Router` agent.
+
+use stdweb::web::event::{ClickEvent, IEvent, IMouseEvent, MouseButton};
+use yew::agent::Bridged;
+use yew::{html, Bridge, Component, ComponentLink, Html, Renderable, ShouldRender};
+
+use crate::router::{Request, Route};
+use crate::routes::{AppRouter, RouteState};
+
+/// Renders a real `<a href>` for a route, so links can be opened in a new tab or copied.
+/// Plain clicks are handled by the router without reloading the page.
+/// The link is marked active while the current route matches it.
+pub struct RouterLink {
+    props: Props,
+    router: Box<dyn Bridge<AppRouter>>,
+    active: bool,
+}
+
+#[derive(Clone, PartialEq, Default)]
+pub struct Props {
+    pub route: Route<RouteState>,
+    pub text: String,
+    /// Only mark the link active on its exact path, rather than on every path below it.
+    pub exact: bool,
+}
+
+pub enum Msg {
+    Navigate,
+    RouteChanged(Route<RouteState>),
+    Ignore,
+}
+
+/// Clicks with a modifier key or another button than the main one keep the native behavior.
+fn is_plain_click(event: &ClickEvent) -> bool {
+    event.button() == MouseButton::Left
+        && !event.ctrl_key()
+        && !event.meta_key()
+        && !event.shift_key()
+        && !event.alt_key()
+}
+
+impl Component for RouterLink {
+    type Message = Msg;
+    type Properties = Props;
+
+    fn create(props: Self::Properties, mut link: ComponentLink<Self>) -> Self {
+        let mut router = AppRouter::bridge(link.send_back(Msg::RouteChanged));
+        router.send(Request::GetCurrentRoute);
+
+        RouterLink {
+            props,
+            router,
+            active: false,
+        }
+    }
+
+    fn update(&mut self, msg: Self::Message) -> ShouldRender {
+        match msg {
+            Msg::Navigate => {
+                self.router.send(Request::ChangeRoute(self.props.route.clone()));
+            }
+            Msg::RouteChanged(route) => {
+                let active = if self.props.exact {
+                    route.path_segments == self.props.route.path_segments
+                } else {
+                    route.starts_with(&self.props.route.path_segments)
+                };
+                let changed = active != self.active;
+                self.active = active;
+                return changed;
+            }
+            Msg::Ignore => {}
+        }
+        false
+    }
+
+    fn change(&mut self, props: Self::Properties) -> ShouldRender {
+        self.props = props;
+        // the link may point somewhere else now, so check it against the current route again
+        self.router.send(Request::GetCurrentRoute);
+        true
+    }
+}
+
+impl Renderable<RouterLink> for RouterLink {
+    fn view(&self) -> Html<Self> {
+        html! {
+            <a href=self.props.route.to_route_string(),
+               class=if self.active { "active" } else { "" },
+               aria-current=if self.active { "page" } else { "false" },
+               onclick=|event| if is_plain_click(&event) {
+                   event.prevent_default();
+                   Msg::Navigate
+               } else {
+                   Msg::Ignore
+               },>
+                { &self.props.text }
+            </a>
+        }
+    }
+}
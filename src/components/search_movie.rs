@@ -1,13 +1,18 @@
 
 use failure::Error;
-use yew::{html, Callback, Component, ComponentLink, Html, Renderable, ShouldRender};
+use yew::agent::Bridged;
+use yew::{html, Bridge, Callback, Component, ComponentLink, Html, Renderable, ShouldRender};
 use yew::services::fetch::StatusCode;
 use yew::services::fetch::FetchTask;
 
 use crate::services::froovie_service::{FroovieService, MovieSearch};
+use crate::services::session;
+use crate::router::Request;
+use crate::routes::{AppRouter, RouteState};
 
 pub struct MovieSearchModel {
     froovie: FroovieService,
+    router: Box<dyn Bridge<AppRouter>>,
     callback: Callback<Result<Vec<MovieSearch>, Error>>,
     add_selection_callback: Callback<Result<StatusCode, Error>>,
     pub result: Vec<MovieSearch>,
@@ -15,23 +20,31 @@ pub struct MovieSearchModel {
     error: Option<String>,
 }
 
+/// Results restored from the history entry, if the search was already run.
+#[derive(Clone, PartialEq, Default)]
+pub struct Props {
+    pub results: Option<Vec<MovieSearch>>,
+}
+
 pub enum Msg {
     SearchResult(String),
     PickSelection(i32),
     SelectionResult(Result<StatusCode, Error>),
     FroovieReady(Result<Vec<MovieSearch>, Error>),
+    Ignore,
 }
 
 impl Component for MovieSearchModel {
     type Message = Msg;
-    type Properties = ();
+    type Properties = Props;
 
-    fn create(_: Self::Properties, mut link: ComponentLink<Self>) -> Self {
+    fn create(props: Self::Properties, mut link: ComponentLink<Self>) -> Self {
         MovieSearchModel {
             froovie: FroovieService::new(),
+            router: AppRouter::bridge(link.send_back(|_| Msg::Ignore)),
             callback: link.send_back(Msg::FroovieReady),
             add_selection_callback: link.send_back(Msg::SelectionResult),
-            result: vec![],
+            result: props.results.unwrap_or_default(),
             task: None,
             error: None,
         }
@@ -44,6 +57,9 @@ impl Component for MovieSearchModel {
                 self.task = Some(task);
             }
             Msg::FroovieReady(Ok(movies)) => {
+                // Keep the results in the history entry, without adding one or re-routing for every keystroke.
+                let state = RouteState { search_results: Some(movies.clone()) };
+                self.router.send(Request::ReplaceState(state));
                 self.result = movies;
             }
             Msg::SelectionResult(Ok(StatusCode::OK)) => {
@@ -60,12 +76,23 @@ impl Component for MovieSearchModel {
                 self.error = Some(error.to_string());
             }
             Msg::PickSelection(moviedb_id) => {
-                let task = self.froovie.post_user_selection(moviedb_id, 1, self.add_selection_callback.clone());
+                let task = self.froovie.post_user_selection(moviedb_id, session::current_user_id(), self.add_selection_callback.clone());
                 self.task = Some(task);
             }
+            Msg::Ignore => return false,
         }
         true
     }
+
+    fn change(&mut self, props: Self::Properties) -> ShouldRender {
+        match props.results {
+            Some(results) => {
+                self.result = results;
+                true
+            }
+            None => false,
+        }
+    }
 }
 
 impl Renderable<MovieSearchModel> for MovieSearchModel {
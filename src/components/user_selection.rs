@@ -1,72 +1,91 @@
 
 
-use failure::Error;
-use yew::{html, Callback, Component, ComponentLink, Html, Renderable, ShouldRender};
-use yew::services::fetch::FetchTask;
+use yew::{html, Component, ComponentLink, Html, Renderable, ShouldRender};
 
-use crate::services::froovie_service::{FroovieService, Selections, Movie};
+use crate::routes;
+use crate::services::froovie_service::{Selections, Movie};
 
 pub struct UserSelectionModel {
-    froovie: FroovieService,
-    callback: Callback<Result<Selections, Error>>,
-    pub selections: Option<Selections>,
-    task: Option<FetchTask>,
-    error: Option<String>,
+    pub selections: Selections,
+    owner: String,
+    highlight: Option<String>,
+}
+
+/// The selections are loaded by the router shell before the component is displayed.
+#[derive(Clone, PartialEq, Default)]
+pub struct Props {
+    /// Id of the user whose selection it is, so shared links open it for anyone.
+    pub owner: String,
+    pub selections: Selections,
+    /// Id of the movie to highlight, taken from the url fragment, e.g. `movie-603`.
+    pub highlight: Option<String>,
 }
 
 pub enum Msg {
-    Selections,
-    FroovieReady(Result<Selections, Error>),
+    /// Copies a link to the movie with this moviedb id.
+    Share(i32),
+}
+
+/// The id of a movie's element, usable as a url fragment linking to it.
+/// It uses the moviedb id, which stays the same for the movie in every selection.
+pub fn movie_anchor(moviedb_id: i32) -> String {
+    format!("movie-{}", moviedb_id)
+}
+
+/// The path of a user's selection, which anyone can open, unlike `/my_selection`.
+pub fn selection_path(user_id: &str) -> String {
+    format!("/users/{}/selection", user_id)
 }
 
 impl Component for UserSelectionModel {
     type Message = Msg;
-    type Properties = ();
+    type Properties = Props;
 
-    fn create(_: Self::Properties, mut link: ComponentLink<Self>) -> Self {
+    fn create(props: Self::Properties, _: ComponentLink<Self>) -> Self {
         UserSelectionModel {
-            froovie: FroovieService::new(),
-            callback: link.send_back(Msg::FroovieReady),
-            selections: None,
-            task: None,
-            error: None,
+            selections: props.selections,
+            owner: props.owner,
+            highlight: props.highlight,
         }
     }
 
     fn update(&mut self, msg: Self::Message) -> ShouldRender {
         match msg {
-            Msg::Selections => {
-                let task = self.froovie.get_user_selection("1", self.callback.clone());
-                self.task = Some(task);
-            }
-            Msg::FroovieReady(Ok(selections)) => {
-                self.selections = Some(selections);
-            }
-            Msg::FroovieReady(Err(error)) => {
-                self.error = Some(error.to_string());
+            Msg::Share(moviedb_id) => {
+                let mut route = routes::route(&selection_path(&self.owner));
+                route.fragment = Some(movie_anchor(moviedb_id));
+                routes::copy_link(&route);
             }
         }
+        false
+    }
+
+    fn change(&mut self, props: Self::Properties) -> ShouldRender {
+        self.selections = props.selections;
+        self.owner = props.owner;
+        self.highlight = props.highlight;
         true
     }
 }
 
 impl Renderable<UserSelectionModel> for UserSelectionModel {
     fn view(&self) -> Html<Self> {
-        let view_movie = |movie| html! {
-            <li> { movie } </li>
+        let view_movie = |movie: &Movie| {
+            let anchor = movie_anchor(movie.moviedb_id);
+            let highlighted = self.highlight.as_ref() == Some(&anchor);
+            let moviedb_id = movie.moviedb_id;
+            html! {
+                <li id=anchor, class=if highlighted { "highlighted" } else { "" },>
+                    { &movie.title }
+                    <button onclick=|_| Msg::Share(moviedb_id),>{ "Share" }</button>
+                </li>
+            }
         };
 
-        let selections = self.selections.as_ref();
-        let movies: Vec<Movie> = selections.iter()
-            .flat_map(|user|user.movies.clone())
-            .collect(); 
-
         html! {
             <div>
-                <button onclick=|_| Msg::Selections,>{ "Get  " }</button>
-                <ul> { for movies.iter().map(|movie| view_movie(movie.title.clone())) } </ul>
-                <p> { &format!("Error status : {:?}", &self.error) } </p>
+                <ul> { for self.selections.movies.iter().map(view_movie) } </ul>
             </div>
         }
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,265 @@
+use failure::Error;
+use yew::{html, Callback, Component, ComponentLink, Html, Renderable, ShouldRender};
+use yew::services::fetch::FetchTask;
+
+use crate::candidates::Candidate;
+use crate::services::froovie_service::{Ballot, FroovieService, VotingMethod, MAX_SCORE};
+use crate::services::session;
+
+/// The ballot of the current user on a movie night, filled in with the voting method the host picked.
+pub struct VoteModel {
+    props: Props,
+    /// The ballot being filled in.
+    ballot: Ballot,
+    froovie: FroovieService,
+    callback: Callback<Result<Ballot, Error>>,
+    task: Option<FetchTask>,
+    saved: bool,
+    error: Option<String>,
+}
+
+#[derive(Clone, PartialEq, Default)]
+pub struct Props {
+    pub movie_night_id: i32,
+    pub voting_method: VotingMethod,
+    /// The candidates that can be voted for.
+    pub candidates: Vec<Candidate>,
+    /// The ballot the user already cast, if any.
+    pub ballot: Option<Ballot>,
+    /// Called with the ballot once it is saved.
+    pub onvote: Option<Callback<Ballot>>,
+}
+
+pub enum Msg {
+    Rank(i32),
+    Unrank(i32),
+    MoveUp(usize),
+    MoveDown(usize),
+    ToggleApproved(i32),
+    SetScore(i32, u8),
+    Choose(i32),
+    Submit,
+    Submitted(Result<Ballot, Error>),
+}
+
+fn ballot(ballot: &Option<Ballot>) -> Ballot {
+    ballot.clone().unwrap_or_else(|| Ballot {
+        voter: session::current_user_id(),
+        ..Ballot::default()
+    })
+}
+
+impl VoteModel {
+    fn title(&self, moviedb_id: i32) -> String {
+        self.props.candidates.iter()
+            .find(|candidate| candidate.moviedb_id() == moviedb_id)
+            .map(|candidate| candidate.movie.title.clone())
+            .unwrap_or_else(|| format!("Movie {}", moviedb_id))
+    }
+
+    fn score(&self, moviedb_id: i32) -> u8 {
+        self.ballot.scores.iter()
+            .find(|(scored, _)| *scored == moviedb_id)
+            .map(|(_, score)| *score)
+            .unwrap_or(0)
+    }
+
+    /// Whether the ballot counts for a candidate with the voting method of the movie night.
+    fn is_filled(&self) -> bool {
+        match self.props.voting_method {
+            VotingMethod::Ranked => !self.ballot.ranking.is_empty(),
+            VotingMethod::Approval => !self.ballot.approved.is_empty(),
+            VotingMethod::Score => self.ballot.scores.iter().any(|(_, score)| *score > 0),
+            VotingMethod::Plurality => self.ballot.choice.is_some(),
+        }
+    }
+
+    fn view_ranked(&self) -> Html<Self> {
+        let ranking = &self.ballot.ranking;
+        let last = ranking.len().saturating_sub(1);
+        let view_ranked = |(position, moviedb_id): (usize, &i32)| {
+            let moviedb_id = *moviedb_id;
+            html! {
+                <li>
+                    { format!("{}. {}", position + 1, self.title(moviedb_id)) }
+                    <button onclick=|_| Msg::MoveUp(position), disabled=position == 0,>{ "Up" }</button>
+                    <button onclick=|_| Msg::MoveDown(position), disabled=position == last,>{ "Down" }</button>
+                    <button onclick=|_| Msg::Unrank(moviedb_id),>{ "Remove" }</button>
+                </li>
+            }
+        };
+        let view_unranked = |candidate: &Candidate| {
+            let moviedb_id = candidate.moviedb_id();
+            html! {
+                <li>
+                    { &candidate.movie.title }
+                    <button onclick=|_| Msg::Rank(moviedb_id),>{ "Rank" }</button>
+                </li>
+            }
+        };
+
+        html! {
+            <>
+                <h3> { "Your ranking" } </h3>
+                <ol> { for ranking.iter().enumerate().map(view_ranked) } </ol>
+                <h3> { "Not ranked" } </h3>
+                <ul> { for self.props.candidates.iter()
+                    .filter(|candidate| !ranking.contains(&candidate.moviedb_id()))
+                    .map(view_unranked) } </ul>
+            </>
+        }
+    }
+
+    fn view_candidate(&self, candidate: &Candidate) -> Html<Self> {
+        let moviedb_id = candidate.moviedb_id();
+        let input = match self.props.voting_method {
+            VotingMethod::Approval => html! {
+                <input type="checkbox",
+                    checked=self.ballot.approved.contains(&moviedb_id),
+                    onclick=|_| Msg::ToggleApproved(moviedb_id),/>
+            },
+            VotingMethod::Plurality => html! {
+                <input type="radio", name="choice",
+                    checked=self.ballot.choice == Some(moviedb_id),
+                    onclick=|_| Msg::Choose(moviedb_id),/>
+            },
+            VotingMethod::Score => {
+                let score = self.score(moviedb_id);
+                html! {
+                    <span class="stars",>
+                        { for (0..=MAX_SCORE).map(|stars| {
+                            let class = if stars > 0 && stars <= score { "star selected" } else { "star" };
+                            let text = if stars == 0 { "0".to_string() } else { "★".repeat(usize::from(stars)) };
+                            html! {
+                                <button class=class, onclick=|_| Msg::SetScore(moviedb_id, stars),>{ text }</button>
+                            }
+                        }) }
+                    </span>
+                }
+            }
+            VotingMethod::Ranked => html! { <></> },
+        };
+
+        html! {
+            <li>
+                <label> { input } { &candidate.movie.title } </label>
+            </li>
+        }
+    }
+}
+
+impl Component for VoteModel {
+    type Message = Msg;
+    type Properties = Props;
+
+    fn create(props: Self::Properties, mut link: ComponentLink<Self>) -> Self {
+        VoteModel {
+            ballot: ballot(&props.ballot),
+            props,
+            froovie: FroovieService::new(),
+            callback: link.send_back(Msg::Submitted),
+            task: None,
+            saved: false,
+            error: None,
+        }
+    }
+
+    fn update(&mut self, msg: Self::Message) -> ShouldRender {
+        let ranking = &mut self.ballot.ranking;
+        match msg {
+            Msg::Rank(moviedb_id) => {
+                if !ranking.contains(&moviedb_id) {
+                    ranking.push(moviedb_id);
+                }
+            }
+            Msg::Unrank(moviedb_id) => {
+                ranking.retain(|id| *id != moviedb_id);
+            }
+            Msg::MoveUp(position) => {
+                if position > 0 && position < ranking.len() {
+                    ranking.swap(position - 1, position);
+                }
+            }
+            Msg::MoveDown(position) => {
+                if position + 1 < ranking.len() {
+                    ranking.swap(position, position + 1);
+                }
+            }
+            Msg::ToggleApproved(moviedb_id) => {
+                let approved = &mut self.ballot.approved;
+                if approved.contains(&moviedb_id) {
+                    approved.retain(|id| *id != moviedb_id);
+                } else {
+                    approved.push(moviedb_id);
+                }
+            }
+            Msg::SetScore(moviedb_id, score) => {
+                let scores = &mut self.ballot.scores;
+                scores.retain(|(scored, _)| *scored != moviedb_id);
+                scores.push((moviedb_id, score.min(MAX_SCORE)));
+            }
+            Msg::Choose(moviedb_id) => {
+                self.ballot.choice = Some(moviedb_id);
+            }
+            Msg::Submit => {
+                let task = self.froovie.submit_ballot(self.props.movie_night_id, &self.ballot, self.callback.clone());
+                self.task = Some(task);
+                return true;
+            }
+            Msg::Submitted(Ok(ballot)) => {
+                self.task = None;
+                self.saved = true;
+                self.error = None;
+                if let Some(ref onvote) = self.props.onvote {
+                    onvote.emit(ballot);
+                }
+                return true;
+            }
+            Msg::Submitted(Err(error)) => {
+                self.task = None;
+                self.error = Some(error.to_string());
+                return true;
+            }
+        }
+        // The ballot was edited.
+        self.saved = false;
+        true
+    }
+
+    fn change(&mut self, props: Self::Properties) -> ShouldRender {
+        // Keep the ballot being filled in, unless another one was loaded.
+        if props.ballot != self.props.ballot {
+            self.ballot = ballot(&props.ballot);
+        }
+        self.props = props;
+        true
+    }
+}
+
+impl Renderable<VoteModel> for VoteModel {
+    fn view(&self) -> Html<Self> {
+        let ballot = match self.props.voting_method {
+            VotingMethod::Ranked => self.view_ranked(),
+            _ => html! {
+                <ul> { for self.props.candidates.iter().map(|candidate| self.view_candidate(candidate)) } </ul>
+            },
+        };
+        let saved = if self.saved {
+            html! { <p class="saved",> { "Your ballot is saved." } </p> }
+        } else {
+            html! { <></> }
+        };
+
+        html! {
+            <div class="vote",>
+                <p> { self.props.voting_method.description() } </p>
+                { ballot }
+                <button onclick=|_| Msg::Submit, disabled=self.task.is_some() || !self.is_filled(),>
+                    { "Submit my ballot" }
+                </button>
+                { saved }
+                { for self.error.iter().map(|error| html! { <p class="error",> { error } </p> }) }
+            </div>
+        }
+    }
+}
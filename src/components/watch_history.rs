@@ -0,0 +1,69 @@
+use yew::{html, Component, ComponentLink, Html, Renderable, ShouldRender};
+
+use crate::components::movie_night::display_date;
+use crate::components::router_link::RouterLink;
+use crate::routes;
+use crate::services::froovie_service::WatchedMovie;
+
+/// The movies the group watched, loaded by the router shell, the most recent first.
+pub struct WatchHistoryModel {
+    history: Vec<WatchedMovie>,
+}
+
+#[derive(Clone, PartialEq, Default)]
+pub struct Props {
+    pub history: Vec<WatchedMovie>,
+}
+
+fn most_recent_first(mut history: Vec<WatchedMovie>) -> Vec<WatchedMovie> {
+    history.sort_by(|a, b| b.date.cmp(&a.date));
+    history
+}
+
+impl Component for WatchHistoryModel {
+    type Message = ();
+    type Properties = Props;
+
+    fn create(props: Self::Properties, _: ComponentLink<Self>) -> Self {
+        WatchHistoryModel {
+            history: most_recent_first(props.history),
+        }
+    }
+
+    fn update(&mut self, _: Self::Message) -> ShouldRender {
+        false
+    }
+
+    fn change(&mut self, props: Self::Properties) -> ShouldRender {
+        self.history = most_recent_first(props.history);
+        true
+    }
+}
+
+impl Renderable<WatchHistoryModel> for WatchHistoryModel {
+    fn view(&self) -> Html<Self> {
+        let view_watched = |watched: &WatchedMovie| {
+            let attendees = watched.attendees.iter()
+                .map(i32::to_string)
+                .collect::<Vec<String>>()
+                .join(", ");
+            html! {
+                <li class="watched",>
+                    <p> { format!("{} - {}", display_date(&watched.date), watched.movie.title) } </p>
+                    <p>
+                        <RouterLink: route=routes::route(&format!("/movie_nights/{}", watched.movie_night_id)), text=watched.title.clone(),/>
+                    </p>
+                    <p> { format!("Attendees: {}", attendees) } </p>
+                </li>
+            }
+        };
+
+        if self.history.is_empty() {
+            return html! { <p> { "No movie watched yet." } </p> };
+        }
+
+        html! {
+            <ul class="watch-history",> { for self.history.iter().map(view_watched) } </ul>
+        }
+    }
+}
@@ -0,0 +1,375 @@
+//! iCalendar (RFC 5545) export of movie nights, so they can be added to a calendar.
+
+use crate::services::froovie_service::{Movie, MovieNight, Rsvp};
+
+/// How long a movie night lasts when the runtime of its movie is not known, in minutes.
+const DEFAULT_DURATION: u32 = 120;
+
+/// Lines longer than this, in octets and without the line break, are folded.
+const MAX_LINE_LENGTH: usize = 75;
+
+/// Escapes a TEXT property value.
+pub fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            ';' => escaped.push_str("\\;"),
+            ',' => escaped.push_str("\\,"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => {}
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Folds a content line into lines of at most 75 octets, each continuation starting with a space,
+/// never splitting a character. Every line ends with CRLF.
+pub fn fold(line: &str) -> String {
+    let mut folded = String::with_capacity(line.len() + 2);
+    let mut length = 0;
+    for c in line.chars() {
+        if length + c.len_utf8() > MAX_LINE_LENGTH {
+            folded.push_str("\r\n ");
+            // The leading space counts towards the length of the continuation line.
+            length = 1;
+        }
+        folded.push(c);
+        length += c.len_utf8();
+    }
+    folded.push_str("\r\n");
+    folded
+}
+
+fn is_leap_year(year: u32) -> bool {
+    (year % 4 == 0 && year % 100 != 0) || year % 400 == 0
+}
+
+fn days_in_month(year: u32, month: u32) -> u32 {
+    match month {
+        2 if is_leap_year(year) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+/// A local date and time, as (year, month, day, hour, minute).
+type DateTime = (u32, u32, u32, u32, u32);
+
+/// Parses a date formatted as `YYYY-MM-DDTHH:MM`, ignoring anything after the minutes.
+fn parse_date(date: &str) -> Option<DateTime> {
+    let number = |range: std::ops::Range<usize>| date.get(range).and_then(|digits| digits.parse().ok());
+    let separators = (date.get(4..5), date.get(7..8), date.get(10..11), date.get(13..14));
+    if separators != (Some("-"), Some("-"), Some("T"), Some(":")) {
+        return None;
+    }
+    let date_time = (number(0..4)?, number(5..7)?, number(8..10)?, number(11..13)?, number(14..16)?);
+    let (year, month, day, hour, minute) = date_time;
+    if month == 0 || month > 12 || day == 0 || day > days_in_month(year, month) || hour > 23 || minute > 59 {
+        return None;
+    }
+    Some(date_time)
+}
+
+/// Adds minutes to a date, carrying over into the following days, months and years.
+fn add_minutes((mut year, mut month, mut day, hour, minute): DateTime, minutes: u32) -> DateTime {
+    let total = hour * 60 + minute + minutes;
+    let mut days = total / (24 * 60);
+    let minutes = total % (24 * 60);
+    while days > 0 {
+        days -= 1;
+        day += 1;
+        if day > days_in_month(year, month) {
+            day = 1;
+            month += 1;
+            if month > 12 {
+                month = 1;
+                year += 1;
+            }
+        }
+    }
+    (year, month, day, minutes / 60, minutes % 60)
+}
+
+/// Formats a date as a floating DATE-TIME, in the local time of whoever imports it.
+fn format_date((year, month, day, hour, minute): DateTime) -> String {
+    format!("{:04}{:02}{:02}T{:02}{:02}00", year, month, day, hour, minute)
+}
+
+fn participation_status(rsvp: Option<Rsvp>) -> &'static str {
+    match rsvp {
+        Some(Rsvp::Going) => "ACCEPTED",
+        Some(Rsvp::Maybe) => "TENTATIVE",
+        Some(Rsvp::NotGoing) => "DECLINED",
+        None => "NEEDS-ACTION",
+    }
+}
+
+/// The calendar address of a user. Users have no email, so it is a urn naming them.
+fn user_address(user_id: i32) -> String {
+    format!("urn:froovie:user:{}", user_id)
+}
+
+/// The content lines of the VEVENT for a movie night, not folded yet.
+///
+/// The event lasts the runtime of the chosen movie when it is known.
+/// `stamp` is the UTC time of the export, formatted as `YYYYMMDDTHHMMSSZ`, and `url` links to the movie night.
+/// Movie nights with an invalid date have no event.
+pub fn event(movie_night: &MovieNight, movie: Option<&Movie>, stamp: &str, url: &str) -> Option<Vec<String>> {
+    let start = parse_date(&movie_night.date)?;
+    let duration = movie.and_then(|movie| movie.runtime).unwrap_or(DEFAULT_DURATION);
+    let end = add_minutes(start, duration);
+
+    let mut description = String::new();
+    if let Some(movie) = movie {
+        description.push_str(&format!("{}\n\n{}\n\n", movie.title, movie.description));
+    }
+    description.push_str(url);
+
+    let mut lines = vec![
+        "BEGIN:VEVENT".to_string(),
+        format!("UID:movie-night-{}@froovie", movie_night.id),
+        format!("DTSTAMP:{}", stamp),
+        format!("DTSTART:{}", format_date(start)),
+        format!("DTEND:{}", format_date(end)),
+        format!("SUMMARY:{}", escape(&movie_night.title)),
+        format!("LOCATION:{}", escape(&movie_night.location)),
+        format!("DESCRIPTION:{}", escape(&description)),
+        format!("URL:{}", url),
+        format!("STATUS:{}", if movie_night.cancelled { "CANCELLED" } else { "CONFIRMED" }),
+        format!("ORGANIZER;CN=User {}:{}", movie_night.host_id, user_address(movie_night.host_id)),
+    ];
+    for &participant in &movie_night.participants {
+        lines.push(format!(
+            "ATTENDEE;CN=User {};PARTSTAT={}:{}",
+            participant,
+            participation_status(movie_night.rsvp(participant)),
+            user_address(participant),
+        ));
+    }
+    lines.push("END:VEVENT".to_string());
+    Some(lines)
+}
+
+/// A VCALENDAR holding the given events, folded and with CRLF line breaks.
+pub fn calendar(events: Vec<Vec<String>>) -> String {
+    let mut lines = vec![
+        "BEGIN:VCALENDAR".to_string(),
+        "VERSION:2.0".to_string(),
+        "PRODID:-//Froovie//Movie nights//EN".to_string(),
+        "CALSCALE:GREGORIAN".to_string(),
+    ];
+    lines.extend(events.into_iter().flatten());
+    lines.push("END:VCALENDAR".to_string());
+    lines.iter().map(|line| fold(line)).collect()
+}
+
+/// The calendar of the movie nights a user takes part in that are still to come and not cancelled.
+///
+/// This is what the calendar feed serves, see `froovie_service::movie_nights_feed_url`.
+/// `now` is the local time, formatted like movie night dates, and `url` links to a movie night.
+pub fn upcoming<F>(movie_nights: &[MovieNight], user_id: i32, now: &str, stamp: &str, url: F) -> String
+    where F: Fn(&MovieNight) -> String
+{
+    let events = movie_nights.iter()
+        .filter(|movie_night| !movie_night.cancelled && movie_night.date.as_str() >= now)
+        .filter(|movie_night| movie_night.participants.contains(&user_id))
+        .filter_map(|movie_night| event(movie_night, None, stamp, &url(movie_night)))
+        .collect();
+    calendar(events)
+}
+
+/// The current UTC time, formatted for DTSTAMP.
+pub fn stamp() -> String {
+    let stamp = js! {
+        return new Date().toISOString().replace(/[-:]/g, "").slice(0, 15) + "Z";
+    };
+    stamp.into_string().unwrap_or_default()
+}
+
+/// Has the browser download a calendar file.
+pub fn download(file_name: &str, calendar: &str) {
+    js! { @(no_return)
+        var link = document.createElement("a");
+        link.href = "data:text/calendar;charset=utf-8," + encodeURIComponent(@{calendar});
+        link.download = @{file_name};
+        document.body.appendChild(link);
+        link.click();
+        document.body.removeChild(link);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::froovie_service::Attendance;
+
+    const URL: &str = "https://froovie.example/movie_nights/42";
+
+    fn movie_night() -> MovieNight {
+        MovieNight {
+            id: 42,
+            title: "Friday, movies; snacks".into(),
+            date: "2025-12-31T23:00".into(),
+            location: "Paul's \\ place".into(),
+            host_id: 1,
+            participants: vec![1, 2, 3],
+            attendances: vec![
+                Attendance { participant: 1, rsvp: Rsvp::Going },
+                Attendance { participant: 2, rsvp: Rsvp::Maybe },
+            ],
+            ..MovieNight::default()
+        }
+    }
+
+    fn movie(runtime: Option<u32>) -> Movie {
+        Movie {
+            id: 7,
+            moviedb_id: 949,
+            title: "Heat".into(),
+            description: "Cops\nand robbers".into(),
+            runtime,
+        }
+    }
+
+    /// The DTEND line of the event of a movie night starting at `date`.
+    fn end(date: &str, movie: Option<&Movie>) -> String {
+        let movie_night = MovieNight { date: date.into(), ..movie_night() };
+        let event = event(&movie_night, movie, "20251201T120000Z", URL).expect("the date is valid");
+        event.into_iter().find(|line| line.starts_with("DTEND:")).expect("every event ends")
+    }
+
+    #[test]
+    fn calendar_matches_the_reference() {
+        let event = event(&movie_night(), Some(&movie(Some(90))), "20251201T120000Z", URL).expect("the date is valid");
+
+        let expected = concat!(
+            "BEGIN:VCALENDAR\r\n",
+            "VERSION:2.0\r\n",
+            "PRODID:-//Froovie//Movie nights//EN\r\n",
+            "CALSCALE:GREGORIAN\r\n",
+            "BEGIN:VEVENT\r\n",
+            "UID:movie-night-42@froovie\r\n",
+            "DTSTAMP:20251201T120000Z\r\n",
+            "DTSTART:20251231T230000\r\n",
+            "DTEND:20260101T003000\r\n",
+            "SUMMARY:Friday\\, movies\\; snacks\r\n",
+            "LOCATION:Paul's \\\\ place\r\n",
+            "DESCRIPTION:Heat\\n\\nCops\\nand robbers\\n\\nhttps://froovie.example/movie_nigh\r\n",
+            " ts/42\r\n",
+            "URL:https://froovie.example/movie_nights/42\r\n",
+            "STATUS:CONFIRMED\r\n",
+            "ORGANIZER;CN=User 1:urn:froovie:user:1\r\n",
+            "ATTENDEE;CN=User 1;PARTSTAT=ACCEPTED:urn:froovie:user:1\r\n",
+            "ATTENDEE;CN=User 2;PARTSTAT=TENTATIVE:urn:froovie:user:2\r\n",
+            "ATTENDEE;CN=User 3;PARTSTAT=NEEDS-ACTION:urn:froovie:user:3\r\n",
+            "END:VEVENT\r\n",
+            "END:VCALENDAR\r\n",
+        );
+        assert_eq!(calendar(vec![event]), expected);
+    }
+
+    #[test]
+    fn cancelled_movie_nights_are_cancelled_events() {
+        let movie_night = MovieNight { cancelled: true, ..movie_night() };
+
+        let event = event(&movie_night, None, "20251201T120000Z", URL).expect("the date is valid");
+
+        assert!(event.contains(&"STATUS:CANCELLED".to_string()));
+    }
+
+    #[test]
+    fn escape_handles_every_special_character() {
+        assert_eq!(escape("a;b,c\\d\ne\r\nf"), "a\\;b\\,c\\\\d\\ne\\nf");
+    }
+
+    #[test]
+    fn short_lines_are_not_folded() {
+        let line = "a".repeat(75);
+
+        assert_eq!(fold(&line), format!("{}\r\n", line));
+    }
+
+    #[test]
+    fn long_lines_are_folded_at_75_octets() {
+        let line = "a".repeat(76);
+
+        assert_eq!(fold(&line), format!("{}\r\n a\r\n", "a".repeat(75)));
+    }
+
+    #[test]
+    fn folding_never_splits_a_character() {
+        // The euro sign takes 3 octets, only 2 are left on the first line.
+        let line = format!("{}€b", "a".repeat(73));
+
+        assert_eq!(fold(&line), format!("{}\r\n €b\r\n", "a".repeat(73)));
+    }
+
+    #[test]
+    fn continuation_lines_count_their_leading_space() {
+        let line = "é".repeat(100);
+
+        let folded = fold(&line);
+
+        assert!(folded.ends_with("\r\n"));
+        let lines: Vec<&str> = folded.trim_end_matches("\r\n").split("\r\n").collect();
+        assert_eq!(lines.iter().map(|line| line.len()).collect::<Vec<usize>>(), vec![74, 75, 53]);
+        assert_eq!(folded.replace("\r\n ", "").trim_end_matches("\r\n"), line);
+    }
+
+    #[test]
+    fn add_minutes_carries_over() {
+        assert_eq!(add_minutes((2025, 6, 14, 20, 0), 95), (2025, 6, 14, 21, 35));
+        assert_eq!(add_minutes((2025, 6, 14, 23, 30), 45), (2025, 6, 15, 0, 15));
+        assert_eq!(add_minutes((2025, 3, 31, 23, 0), 120), (2025, 4, 1, 1, 0));
+        assert_eq!(add_minutes((2025, 12, 31, 22, 0), 150), (2026, 1, 1, 0, 30));
+        assert_eq!(add_minutes((2024, 2, 28, 23, 0), 120), (2024, 2, 29, 1, 0));
+        assert_eq!(add_minutes((2025, 2, 28, 23, 0), 120), (2025, 3, 1, 1, 0));
+        assert_eq!(add_minutes((2025, 1, 30, 12, 0), 3 * 24 * 60), (2025, 2, 2, 12, 0));
+    }
+
+    #[test]
+    fn events_last_the_runtime_of_the_movie_or_the_default() {
+        assert_eq!(end("2025-06-14T20:00", Some(&movie(Some(95)))), "DTEND:20250614T213500");
+        assert_eq!(end("2025-06-14T20:00", Some(&movie(None))), "DTEND:20250614T220000");
+        assert_eq!(end("2025-06-14T20:00", None), "DTEND:20250614T220000");
+        assert_eq!(end("2025-12-31T23:30", None), "DTEND:20260101T013000");
+    }
+
+    #[test]
+    fn parse_date_checks_the_calendar() {
+        assert_eq!(parse_date("2024-02-29T20:00"), Some((2024, 2, 29, 20, 0)));
+        assert_eq!(parse_date("2025-06-14T20:00:30"), Some((2025, 6, 14, 20, 0)));
+        assert_eq!(parse_date("2025-02-29T20:00"), None);
+        assert_eq!(parse_date("2025-04-31T20:00"), None);
+        assert_eq!(parse_date("2025-13-01T20:00"), None);
+        assert_eq!(parse_date("2025-06-14T24:00"), None);
+        assert_eq!(parse_date("2025-06-14 20:00"), None);
+        assert_eq!(parse_date("2025-06-14"), None);
+    }
+
+    #[test]
+    fn movie_nights_with_an_invalid_date_have_no_event() {
+        let movie_night = MovieNight { date: "2025-02-29T20:00".into(), ..movie_night() };
+
+        assert_eq!(event(&movie_night, None, "20251201T120000Z", URL), None);
+    }
+
+    #[test]
+    fn upcoming_keeps_the_future_movie_nights_of_the_user() {
+        let movie_nights = vec![
+            MovieNight { id: 1, date: "2025-06-01T20:00".into(), ..movie_night() },
+            MovieNight { id: 2, date: "2025-07-01T20:00".into(), ..movie_night() },
+            MovieNight { id: 3, date: "2025-07-01T20:00".into(), cancelled: true, ..movie_night() },
+            MovieNight { id: 4, date: "2025-07-01T20:00".into(), participants: vec![1, 2], ..movie_night() },
+        ];
+        let url = |movie_night: &MovieNight| format!("https://froovie.example/movie_nights/{}", movie_night.id);
+
+        let calendar = upcoming(&movie_nights, 3, "2025-06-14T12:00", "20250614T100000Z", url);
+
+        let uids: Vec<&str> = calendar.split("\r\n").filter(|line| line.starts_with("UID:")).collect();
+        assert_eq!(uids, vec!["UID:movie-night-2@froovie"]);
+    }
+}
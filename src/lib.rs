@@ -1,31 +1,171 @@
-mod router;
-mod routing;
+#[macro_use]
+extern crate stdweb;
+
+pub mod router;
+pub mod routing;
+mod routes;
+mod candidates;
+mod voting;
+mod ics;
 mod components;
 use components::user_selection::UserSelectionModel;
 use components::search_movie::MovieSearchModel;
+use components::nav::Nav;
+use components::movie_night::MovieNightModel;
+use components::movie_night_form::MovieNightFormModel;
+use components::movie_night_list::MovieNightListModel;
+use components::join::JoinModel;
+use components::watch_history::WatchHistoryModel;
+use components::not_found::NotFoundModel;
 
 pub mod services;
 
-use log::info;
-use router::Route;
-use yew::{html, Bridge, Component, ComponentLink, Html, Renderable, ShouldRender};
+use failure::Error;
+use log::{info, warn};
+use router::{Mount, Route};
+use routes::{AppRouter, RouteState};
+use services::froovie_service::{FroovieService, InviteDetails, MovieNight, Selections, WatchedMovie};
+use services::session;
+use yew::{html, Bridge, Callback, Component, ComponentLink, Html, Renderable, ShouldRender};
 use yew::agent::Bridged;
+use yew::services::fetch::FetchTask;
 
 
+#[derive(Clone, PartialEq)]
 pub enum Child {
     UserSelection,
+    /// The selection of a user, by id, e.g. opened from a shared link.
+    Selection(String),
     MovieSearch,
+    MovieNights,
+    NewMovieNight,
+    /// A movie night, by id. It routes the rest of the path itself.
+    MovieNight(String),
+    /// Joining a movie night with the token of an invite.
+    Join(String),
+    WatchHistory,
+    /// The path of an unknown route.
     PathNotFound(String)
 }
 
+/// Data loaded by the shell before a child is displayed, and passed to it as props.
+pub enum RouteData {
+    Selections(Selections),
+    MovieNights(Vec<MovieNight>),
+    MovieNight(MovieNight),
+    Invite(InviteDetails),
+    WatchHistory(Vec<WatchedMovie>),
+}
+
+enum Loading {
+    /// The child does not load anything.
+    Idle,
+    Pending,
+    Failed(String),
+    Done(RouteData),
+}
+
 pub struct Model {
     child: Child,
-    router: Box<dyn Bridge<router::Router<()>>>
+    /// State of the history entry being displayed.
+    state: RouteState,
+    /// The url being displayed, `None` until the first route is handled.
+    route_string: Option<String>,
+    /// Fragment of the url, naming the item to scroll to and highlight.
+    fragment: Option<String>,
+    /// Where to scroll back to once the data is loaded, when the user came back with the history.
+    scroll_offset: Option<(f64, f64)>,
+    router: Box<dyn Bridge<AppRouter>>,
+    froovie: FroovieService,
+    load_callback: Callback<Result<RouteData, Error>>,
+    load_task: Option<FetchTask>,
+    loading: Loading,
+}
+
+impl Child {
+    /// The route that displays this child.
+    pub fn to_route(&self) -> Route<RouteState> {
+        let path_segments = match self {
+            Child::UserSelection => vec!["my_selection".into()],
+            Child::Selection(ref user_id) => vec!["users".into(), user_id.clone(), "selection".into()],
+            Child::MovieSearch => vec!["movies_search".into()],
+            Child::MovieNights => vec!["movie_nights".into()],
+            Child::NewMovieNight => vec!["movie_nights".into(), "new".into()],
+            Child::MovieNight(ref id) => vec!["movie_nights".into(), id.clone()],
+            Child::Join(ref token) => vec!["join".into(), token.clone()],
+            Child::WatchHistory => vec!["history".into()],
+            Child::PathNotFound(_) => vec!["path_not_found".into()]
+        };
+
+        Route {
+            path_segments,
+            query: None,
+            fragment: None,
+            state: RouteState::default(),
+            scroll_offset: None,
+        }
+    }
+
+    /// Starts loading the data displayed by this child, if it needs any.
+    fn load(&self, froovie: &mut FroovieService, callback: Callback<Result<RouteData, Error>>) -> Option<FetchTask> {
+        match self {
+            Child::UserSelection => Some(froovie.get_user_selection(
+                &session::current_user_id().to_string(),
+                callback.reform(|selections: Result<Selections, Error>| selections.map(RouteData::Selections)),
+            )),
+            Child::Selection(user_id) => Some(froovie.get_user_selection(
+                user_id,
+                callback.reform(|selections: Result<Selections, Error>| selections.map(RouteData::Selections)),
+            )),
+            Child::MovieNights => Some(froovie.list_movie_nights(
+                callback.reform(|movie_nights: Result<Vec<MovieNight>, Error>| movie_nights.map(RouteData::MovieNights)),
+            )),
+            Child::MovieNight(id) => Some(froovie.get_movie_night(
+                id,
+                callback.reform(|movie_night: Result<MovieNight, Error>| movie_night.map(RouteData::MovieNight)),
+            )),
+            Child::Join(token) => Some(froovie.get_invite(
+                token,
+                callback.reform(|invite: Result<InviteDetails, Error>| invite.map(RouteData::Invite)),
+            )),
+            Child::WatchHistory => Some(froovie.get_watch_history(
+                callback.reform(|history: Result<Vec<WatchedMovie>, Error>| history.map(RouteData::WatchHistory)),
+            )),
+            Child::MovieSearch | Child::NewMovieNight | Child::PathNotFound(_) => None,
+        }
+    }
+
+    /// The document title for this child,
+    /// or `None` if the child sets it itself once it knows what it displays.
+    pub fn title(&self) -> Option<String> {
+        match self {
+            Child::UserSelection => Some("My Movies".into()),
+            Child::Selection(user_id) => Some(format!("Movies of user {}", user_id)),
+            Child::MovieSearch => Some("Search a Movie".into()),
+            Child::MovieNights => Some("Movie Nights".into()),
+            Child::NewMovieNight => Some("Plan a movie night".into()),
+            Child::MovieNight(_) => None,
+            Child::Join(_) => Some("Join a movie night".into()),
+            Child::WatchHistory => Some("Watch History".into()),
+            Child::PathNotFound(_) => Some("Page not found".into()),
+        }
+    }
+}
+
+/// Whether displaying `child` at `route_string` loads its data again, given what is displayed.
+/// The first route always loads, moving between the sub-views of a child keeps its data,
+/// and navigating to the same url reloads it.
+fn reloads(displayed: Option<(&Child, &str)>, child: &Child, route_string: &str) -> bool {
+    match displayed {
+        None => true,
+        Some((displayed_child, displayed_route)) => child != displayed_child || route_string == displayed_route,
+    }
 }
 
 pub enum Msg {
-    NavigateTo(Child),
-    HandleRoute(Route<()>)
+    HandleRoute(Route<RouteState>),
+    Loaded(Result<RouteData, Error>),
+    Retry,
 }
 
 impl Component for Model {
@@ -35,58 +175,103 @@ impl Component for Model {
     fn create(_: Self::Properties, mut link: ComponentLink<Self>) -> Self {
 
         let callback = link.send_back(Msg::HandleRoute);
-        let mut router = router::Router::bridge(callback);
+        let mut router = AppRouter::bridge(callback);
+        router.send(router::Request::SetRedirects(routes::redirects()));
 
         // TODO Not sure if this is technically correct. This should be sent _after_ the component has been created.
         // I think the `Component` trait should have a hook called `on_mount()`
         // that is called after the component has been attached to the vdom.
         // It seems like this only works because the JS engine decides to activate the
         // router worker logic after the mounting has finished.
-        router.send(router::Request::GetCurrentRoute);
+        router.send(router::Request::LoadInitialRoute);
 
         Model {
             child: Child::UserSelection, // This should be quickly overwritten by the actual route.
-            router
+            state: RouteState::default(),
+            route_string: None,
+            fragment: None,
+            scroll_offset: None,
+            router,
+            froovie: FroovieService::new(),
+            load_callback: link.send_back(Msg::Loaded),
+            load_task: None,
+            loading: Loading::Idle,
         }
     }
 
     fn update(&mut self, msg: Self::Message) -> ShouldRender {
         match msg {
-            Msg::NavigateTo(child) => {
-
-                let path_segments = match child {
-                    Child::UserSelection => vec!["my_selection".into()],
-                    Child::MovieSearch => vec!["movies_search".into()],
-                    Child::PathNotFound(_) => vec!["path_not_found".into()]
-                };
-
-                let route = router::Route {
-                    path_segments,
-                    query: None,
-                    fragment: None,
-                    state: (),
-                };
-
-                self.router.send(router::Request::ChangeRoute(route));
-                false
-            }
             Msg::HandleRoute(route) => {
                 info!("Routing: {}", route.to_route_string());
                 // Instead of each component selecting which parts of the path are important to it,
                 // it is also possible to match on the `route.to_route_string().as_str()` once
                 // and create enum variants representing the different children and pass them as props.
-                self.child = if let Some(first_segment) = route.path_segments.get(0) {
+                let path = format!("/{}", route.path_segments.join("/"));
+                let child = if let Some(first_segment) = route.path_segments.get(0) {
                    match first_segment.as_str() {
                        "my_selection" => Child::UserSelection,
                        "movies_search" => Child::MovieSearch,
-                        other => Child::PathNotFound(other.into())
+                       "movie_nights" => match route.path_segments.get(1).map(String::as_str) {
+                           None => Child::MovieNights,
+                           Some("new") => Child::NewMovieNight,
+                           Some(id) => Child::MovieNight(id.into()),
+                       },
+                       "history" => Child::WatchHistory,
+                       "users" => match (route.path_segments.get(1), route.path_segments.get(2).map(String::as_str)) {
+                           (Some(user_id), Some("selection")) if route.path_segments.len() == 3 => Child::Selection(user_id.clone()),
+                           _ => Child::PathNotFound(path),
+                       },
+                       "join" => match route.path_segments.get(1) {
+                           Some(token) => Child::Join(token.clone()),
+                           None => Child::PathNotFound(path),
+                       },
+                        _ => Child::PathNotFound(path)
                    }
                 } else {
-                    Child::PathNotFound("path_not_found".into())
+                    Child::PathNotFound(path)
                 };
+                let route_string = route.to_route_string();
+                let displayed = self.route_string.as_ref().map(|displayed| (&self.child, displayed.as_str()));
+                let reload = reloads(displayed, &child, &route_string);
+                self.child = child;
+                self.route_string = Some(route_string);
+                if let Child::PathNotFound(ref path) = self.child {
+                    // Most likely a broken link someone shared.
+                    warn!("Unknown path: '{}'", path);
+                }
+                self.fragment = route.fragment;
+                self.scroll_offset = route.scroll_offset;
+                self.state = route.state;
+                if let Some(title) = self.child.title() {
+                    routes::set_title(&title);
+                }
+                if reload {
+                    self.load();
+                }
 
                 true
             }
+            Msg::Loaded(Ok(data)) => {
+                self.load_task = None;
+                self.loading = Loading::Done(data);
+                // The item named by the fragment, or the offset the user left the page at,
+                // was not rendered when the router scrolled.
+                if self.fragment.is_some() {
+                    routing::scroll_to_fragment(self.fragment.as_ref().map(String::as_str));
+                } else if let Some((x, y)) = self.scroll_offset {
+                    routing::scroll_to(x, y);
+                }
+                true
+            }
+            Msg::Loaded(Err(error)) => {
+                self.load_task = None;
+                self.loading = Loading::Failed(error.to_string());
+                true
+            }
+            Msg::Retry => {
+                self.load();
+                true
+            }
         }
     }
 }
@@ -95,39 +280,144 @@ impl Renderable<Model> for Model {
     fn view(&self) -> Html<Self> {
         html! {
             <div>
-                <nav class="menu",>
-                    <button onclick=|_| Msg::NavigateTo(Child::UserSelection),>{ "My Movies" }</button>
-                    <button onclick=|_| Msg::NavigateTo(Child::MovieSearch),>{ "Search a Movie" }</button>
-                </nav>
+                <Nav: />
                 <div>
-                    {self.child.view()}
+                    {self.view_child()}
                 </div>
             </div>
         }
     }
 }
 
-impl Renderable<Model> for Child {
-    fn view(&self) -> Html<Model> {
-        match *self {
+impl Model {
+    /// Runs the loader of the displayed child. A load still running for the previous child is cancelled.
+    fn load(&mut self) {
+        self.load_task = self.child.load(&mut self.froovie, self.load_callback.clone());
+        self.loading = if self.load_task.is_some() {
+            Loading::Pending
+        } else {
+            Loading::Idle
+        };
+    }
+
+    fn selections(&self) -> Selections {
+        match self.loading {
+            Loading::Done(RouteData::Selections(ref selections)) => selections.clone(),
+            _ => Selections::default(),
+        }
+    }
+
+    fn movie_nights(&self) -> Vec<MovieNight> {
+        match self.loading {
+            Loading::Done(RouteData::MovieNights(ref movie_nights)) => movie_nights.clone(),
+            _ => vec![],
+        }
+    }
+
+    fn movie_night(&self) -> MovieNight {
+        match self.loading {
+            Loading::Done(RouteData::MovieNight(ref movie_night)) => movie_night.clone(),
+            _ => MovieNight::default(),
+        }
+    }
+
+    fn invite(&self) -> InviteDetails {
+        match self.loading {
+            Loading::Done(RouteData::Invite(ref invite)) => invite.clone(),
+            _ => InviteDetails::default(),
+        }
+    }
+
+    fn watch_history(&self) -> Vec<WatchedMovie> {
+        match self.loading {
+            Loading::Done(RouteData::WatchHistory(ref history)) => history.clone(),
+            _ => vec![],
+        }
+    }
+
+    fn view_child(&self) -> Html<Model> {
+        match self.loading {
+            Loading::Pending => return html! {
+                <p class="loading",> { "Loading..." } </p>
+            },
+            Loading::Failed(ref error) => return html! {
+                <div class="error",>
+                    <p> { format!("Could not load this page: {}", error) } </p>
+                    <button onclick=|_| Msg::Retry,>{ "Retry" }</button>
+                </div>
+            },
+            Loading::Idle | Loading::Done(_) => {}
+        }
+
+        match self.child {
             Child::MovieSearch => html! {
                 <>
                     {" Search a movie : "}
-                    <MovieSearchModel: />
+                    <MovieSearchModel: results=self.state.search_results.clone(),/>
                 </>
             },
             Child::UserSelection => html! {
                 <>
                     {"User Selection: "}
-                    <UserSelectionModel: />
+                    <UserSelectionModel: owner=session::current_user_id().to_string(), selections=self.selections(), highlight=self.fragment.clone(),/>
                 </>
             },
-            Child::PathNotFound(ref path) => html! {
+            Child::Selection(ref user_id) => html! {
                 <>
-                    {format!("Invalid path: '{}'", path)}
+                    { format!("Selection of user {}: ", user_id) }
+                    <UserSelectionModel: owner=user_id.clone(), selections=self.selections(), highlight=self.fragment.clone(),/>
                 </>
+            },
+            Child::MovieNights => html! {
+                <MovieNightListModel: movie_nights=self.movie_nights(),/>
+            },
+            Child::NewMovieNight => html! {
+                <MovieNightFormModel: mount=Mount::new(self.child.to_route().path_segments),/>
+            },
+            Child::MovieNight(ref id) => html! {
+                <MovieNightModel: id=id.clone(), mount=Mount::new(self.child.to_route().path_segments), movie_night=self.movie_night(),/>
+            },
+            Child::Join(ref token) => html! {
+                <JoinModel: token=token.clone(), invite=self.invite(),/>
+            },
+            Child::WatchHistory => html! {
+                <WatchHistoryModel: history=self.watch_history(),/>
+            },
+            Child::PathNotFound(ref path) => html! {
+                <NotFoundModel: path=path.clone(),/>
             }
         }
     }
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn the_first_route_loads_even_if_it_is_the_initial_child() {
+        assert!(reloads(None, &Child::UserSelection, "/my_selection"));
+    }
+
+    #[test]
+    fn navigating_to_the_same_url_reloads() {
+        assert!(reloads(Some((&Child::UserSelection, "/my_selection")), &Child::UserSelection, "/my_selection"));
+    }
+
+    #[test]
+    fn navigating_to_another_child_loads_it() {
+        assert!(reloads(Some((&Child::UserSelection, "/my_selection")), &Child::MovieNights, "/movie_nights"));
+    }
+
+    #[test]
+    fn moving_between_sub_views_keeps_the_data() {
+        let movie_night = Child::MovieNight("42".into());
+
+        assert!(!reloads(Some((&movie_night, "/movie_nights/42")), &movie_night, "/movie_nights/42/vote"));
+    }
+
+    #[test]
+    fn selections_of_other_users_are_routed_by_id() {
+        assert_eq!(Child::Selection("7".into()).to_route(), routes::route("/users/7/selection"));
+    }
+}
@@ -1,10 +1,11 @@
 //! Agent that exposes a usable routing interface to components.
 
-use crate::routing::RouteService;
+use crate::routing::{BrowserHistory, HistoryBackend, RouteService};
 use yew::worker::*;
-use log::info;
+use log::{info, warn};
 use serde_derive::{Deserialize, Serialize};
 use std::collections::HashSet;
+use std::hash::Hash;
 use stdweb::Value;
 use stdweb::JsSerialize;
 use stdweb::unstable::TryFrom;
@@ -12,34 +13,117 @@ use serde::Serialize;
 use serde::Deserialize;
 use std::fmt::Debug;
 
-#[derive(Clone, Debug, PartialEq, Serialize, Deserialize) ]
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize) ]
 pub struct Route<T> {
     pub path_segments: Vec<String>,
     pub query: Option<String>,
     pub fragment: Option<String>,
-    pub state: T
+    pub state: T,
+    /// Where the page was scrolled when the user left this route, set when they come back to it
+    /// with the browser's back or forward buttons.
+    /// Views that render the route only once its data has loaded scroll there again then.
+    #[serde(default)]
+    pub scroll_offset: Option<(f64, f64)>,
+}
+
+/// Whether a byte can appear unescaped in a path segment:
+/// the RFC 3986 unreserved characters, the sub-delimiters, ':' and '@'.
+fn is_segment_byte(byte: u8) -> bool {
+    byte.is_ascii_alphanumeric() || b"-._~!$&'()*+,;=:@".contains(&byte)
+}
+
+/// Whether a byte can appear unescaped in a fragment, which also allows '/' and '?'.
+fn is_fragment_byte(byte: u8) -> bool {
+    is_segment_byte(byte) || byte == b'/' || byte == b'?'
+}
+
+fn percent_encode(input: &str, keep: fn(u8) -> bool) -> String {
+    let mut encoded = String::with_capacity(input.len());
+    for byte in input.bytes() {
+        if keep(byte) {
+            encoded.push(byte as char);
+        } else {
+            encoded.push_str(&format!("%{:02X}", byte));
+        }
+    }
+    encoded
+}
+
+/// Percent-encodes a path segment, including any '/' it contains.
+pub fn encode_segment(segment: &str) -> String {
+    percent_encode(segment, is_segment_byte)
+}
+
+/// Percent-encodes a fragment.
+pub fn encode_fragment(fragment: &str) -> String {
+    percent_encode(fragment, is_fragment_byte)
+}
+
+/// Decodes a percent-encoded path segment or fragment.
+/// Malformed escapes are kept as they are, and invalid UTF-8 is replaced.
+pub fn percent_decode(input: &str) -> String {
+    fn hex_value(byte: u8) -> u8 {
+        (byte as char).to_digit(16).expect("checked to be a hex digit") as u8
+    }
+
+    let bytes = input.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%'
+            && i + 2 < bytes.len()
+            && bytes[i + 1].is_ascii_hexdigit()
+            && bytes[i + 2].is_ascii_hexdigit() {
+            decoded.push(hex_value(bytes[i + 1]) * 16 + hex_value(bytes[i + 2]));
+            i += 3;
+        } else {
+            decoded.push(bytes[i]);
+            i += 1;
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
 }
 
 impl<T> Route<T>
-    where T: JsSerialize + Clone + TryFrom<Value> + Default +'static
+    where T: Clone + Default
 {
+    /// Whether the path of this route begins with the given segments.
+    pub fn starts_with(&self, prefix: &[String]) -> bool {
+        self.path_segments.len() >= prefix.len()
+            && self.path_segments.iter().zip(prefix).all(|(segment, expected)| segment == expected)
+    }
+
+    /// Builds the url of the route, percent-encoding the path segments and the fragment.
+    /// The query is used as it is, since its separators are meaningful.
+    ///
+    /// `current_route` reads the same segments back from the url, except for trailing empty segments,
+    /// which it drops so that "/a/" is the same route as "/a". Routes should not end with an empty segment.
     pub fn to_route_string(&self) -> String {
-        let path = self.path_segments.join("/");
+        let path = self.path_segments.iter()
+            .map(|segment| encode_segment(segment))
+            .collect::<Vec<String>>()
+            .join("/");
         let mut path = format!("/{}", path); // add the leading '/'
         if let Some(ref query) = self.query {
             path = format!("{}?{}", path, query);
         }
         if let Some(ref fragment) = self.fragment {
-            path = format!("{}#{}", path, fragment)
+            path = format!("{}#{}", path, encode_fragment(fragment))
         }
         path
     }
 
-    pub fn current_route(route_service: &RouteService<T>) -> Self
+    /// The route of the current url, decoding its path segments and fragment.
+    /// Trailing empty segments are dropped, and an empty query or fragment is `None`.
+    pub fn current_route<H: HistoryBackend<T>>(route_service: &RouteService<T, H>) -> Self
     {
         let path = route_service.get_path(); // guaranteed to always start with a '/'
-        let mut path_segments: Vec<String> = path.split("/").map(String::from).collect();
+        let mut path_segments: Vec<String> = path.split("/").map(percent_decode).collect();
         path_segments.remove(0); // remove empty string that is split from the first '/'
+        // normalize trailing slashes, so that "/" has no segments and "/a/" is the same as "/a"
+        while path_segments.last().map_or(false, String::is_empty) {
+            path_segments.pop();
+        }
 
         let mut query: String = route_service.get_query(); // The first character will be a '?'
         let query: Option<String> = if query.len() > 1 {
@@ -52,7 +136,7 @@ impl<T> Route<T>
         let mut fragment: String = route_service.get_fragment(); // The first character will be a '#'
         let fragment: Option<String> = if fragment.len() > 1 {
             fragment.remove(0);
-            Some(fragment)
+            Some(percent_decode(&fragment))
         } else {
             None
         };
@@ -62,11 +146,163 @@ impl<T> Route<T>
             path_segments,
             query,
             fragment,
-            state: T::default()
+            state: route_service.get_state(),
+            scroll_offset: None,
         }
     }
 }
 
+/// The path a nested component is displayed at, e.g. `/movie_nights/42`.
+/// The component handles the rest of the path itself, and navigates relative to its mount point
+/// without knowing where it was mounted.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Mount {
+    pub base: Vec<String>,
+}
+
+impl Mount {
+    pub fn new(base: Vec<String>) -> Self {
+        Mount { base }
+    }
+
+    /// The segments of the route below the mount point, or `None` if the route is outside of it.
+    pub fn rest<'a, T>(&self, route: &'a Route<T>) -> Option<&'a [String]> {
+        if route.path_segments.len() >= self.base.len()
+            && route.path_segments.iter().zip(&self.base).all(|(segment, base)| segment == base) {
+            Some(&route.path_segments[self.base.len()..])
+        } else {
+            None
+        }
+    }
+
+    /// A mount point nested below this one, for a child that handles its own part of the path.
+    pub fn nest(&self, segment: &str) -> Mount {
+        let mut base = self.base.clone();
+        base.push(segment.to_string());
+        Mount { base }
+    }
+
+    /// Builds a route to a path relative to the mount point.
+    /// An empty path is the mount point itself and each `..` goes up one segment.
+    pub fn route<T: Default>(&self, relative_path: &str) -> Route<T> {
+        let mut path_segments = self.base.clone();
+        for segment in relative_path.split('/').filter(|segment| !segment.is_empty()) {
+            match segment {
+                "." => {}
+                ".." => {
+                    path_segments.pop();
+                }
+                segment => path_segments.push(segment.to_string()),
+            }
+        }
+
+        Route {
+            path_segments,
+            query: None,
+            fragment: None,
+            state: T::default(),
+            scroll_offset: None,
+        }
+    }
+}
+
+/// How many redirects are followed for a single navigation before it is abandoned.
+const MAX_REDIRECTS: usize = 8;
+
+/// An entry of the redirect table, sending every navigation to `from` to `to` instead.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Redirect {
+    from: Vec<String>,
+    to: Vec<String>,
+    /// Whether paths below `from` are redirected as well, keeping their remaining segments.
+    prefix: bool,
+}
+
+impl Redirect {
+    /// Redirects the exact path `from`.
+    pub fn exact(from: &str, to: &str) -> Self {
+        Redirect {
+            from: Self::segments(from),
+            to: Self::segments(to),
+            prefix: false,
+        }
+    }
+
+    /// Redirects `from` and every path below it, e.g. a section that moved.
+    pub fn prefix(from: &str, to: &str) -> Self {
+        Redirect {
+            from: Self::segments(from),
+            to: Self::segments(to),
+            prefix: true,
+        }
+    }
+
+    fn segments(path: &str) -> Vec<String> {
+        path.split('/')
+            .filter(|segment| !segment.is_empty())
+            .map(String::from)
+            .collect()
+    }
+
+    /// Returns the redirected path segments if this redirect matches the route.
+    fn apply<T: Clone + Default>(&self, route: &Route<T>) -> Option<Vec<String>> {
+        let matches = if self.prefix {
+            route.starts_with(&self.from)
+        } else {
+            route.path_segments == self.from
+        };
+        if !matches {
+            return None;
+        }
+        let mut path_segments = self.to.clone();
+        path_segments.extend_from_slice(&route.path_segments[self.from.len()..]);
+        Some(path_segments)
+    }
+}
+
+/// Selects the navigations a guard is consulted for.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum GuardCondition {
+    /// Navigations to a route whose path starts with these segments.
+    Entering(Vec<String>),
+    /// Navigations away from a route whose path starts with these segments.
+    Leaving(Vec<String>),
+}
+
+/// What happens to a navigation matched by a guard.
+/// Navigations that no guard matches are allowed.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum GuardAction<T> {
+    /// Cancels the navigation.
+    Deny,
+    /// Navigates to another route instead.
+    Redirect(Route<T>),
+    /// Asks the user, and cancels the navigation if they decline.
+    Confirm(String),
+}
+
+/// A rule registered by a subscriber that can veto or reroute navigations.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Guard<T> {
+    pub condition: GuardCondition,
+    pub action: GuardAction<T>,
+}
+
+impl<T: Clone + Default> Guard<T> {
+    fn applies(&self, from: &Route<T>, to: &Route<T>) -> bool {
+        match self.condition {
+            GuardCondition::Entering(ref prefix) => to.starts_with(prefix),
+            GuardCondition::Leaving(ref prefix) => from.starts_with(prefix) && !to.starts_with(prefix),
+        }
+    }
+}
+
+enum Verdict<T> {
+    Allow,
+    Deny,
+    Redirect(Route<T>),
+}
+
 pub enum Msg<T>
     where T: JsSerialize + Clone + Debug + TryFrom<Value> + 'static
 {
@@ -85,27 +321,302 @@ pub enum Request<T> {
     ChangeRoute(Route<T>),
     /// Changes the route using a RouteInfo struct, but does not alert connected components to the route change.
     ChangeRouteNoBroadcast(Route<T>),
-    GetCurrentRoute
+    /// Replaces the current history entry with the route and alerts connected components to the route change.
+    /// Unlike `ChangeRoute`, the back button will not return to the replaced route.
+    ReplaceRoute(Route<T>),
+    /// Replaces the state of the current history entry, keeping its url,
+    /// but does not alert connected components, e.g. to remember what a view displays.
+    ReplaceState(T),
+    /// Goes one entry back in the history, like the browser's back button.
+    Back,
+    /// Goes one entry forward in the history, like the browser's forward button.
+    Forward,
+    /// Moves through the history by the given number of entries, backward if negative.
+    Go(i32),
+    /// Answers the sender with the current route, without changing anything.
+    GetCurrentRoute,
+    /// Runs the redirects and the guards against the url the app was opened at,
+    /// fixes up the url bar, and answers the sender with the resulting route.
+    /// Sent once by the app shell, after its redirects are set.
+    LoadInitialRoute,
+    /// Alerts connected components to the current route again, without changing it,
+    /// e.g. so they reload what they display.
+    RefreshRoute,
+    /// Replaces the redirect table, applied to every navigation before the guards.
+    SetRedirects(Vec<Redirect>),
+    /// Registers a guard that is consulted before every navigation, including browser back and forward.
+    AddGuard(Guard<T>),
+    /// Removes every guard registered by the sender.
+    ClearGuards,
 }
 
 impl <T> Transferable for Request <T>
     where for <'de> T: Serialize + Deserialize<'de>
 {}
 
+/// The routing logic of the `Router` agent, independent of the agent machinery and of the browser.
+///
+/// `S` identifies the subscribers, and every operation returns the routes to deliver to them,
+/// so the core can be driven by the agent or by a test with a `MemoryHistory`.
+pub struct RouterCore<T, H, S> {
+    route_service: RouteService<T, H>,
+    /// A list of all entities connected to the router.
+    /// When a route changes, either initiated by the browser or by the app,
+    /// the route change will be broadcast to all listening entities.
+    subscribers: HashSet<S>,
+    /// Guards and the entity that registered them, consulted in registration order.
+    guards: Vec<(S, Guard<T>)>,
+    redirects: Vec<Redirect>,
+    /// The last route that was allowed through the guards.
+    current: Route<T>,
+    /// The position of the history entry displaying `current`.
+    position: i32,
+}
+
+impl<T, H, S> RouterCore<T, H, S>
+    where T: Clone + Debug + Default + PartialEq,
+          H: HistoryBackend<T>,
+          S: Copy + Eq + Hash
+{
+    pub fn new(route_service: RouteService<T, H>) -> Self {
+        let current = Route::current_route(&route_service);
+        let position = route_service.position();
+
+        RouterCore {
+            route_service,
+            subscribers: HashSet::new(),
+            guards: Vec::new(),
+            redirects: Vec::new(),
+            current,
+            position,
+        }
+    }
+
+    pub fn route_service(&self) -> &RouteService<T, H> {
+        &self.route_service
+    }
+
+    pub fn route_service_mut(&mut self) -> &mut RouteService<T, H> {
+        &mut self.route_service
+    }
+
+    /// The route currently displayed.
+    pub fn current(&self) -> &Route<T> {
+        &self.current
+    }
+
+    pub fn connect(&mut self, id: S) {
+        self.subscribers.insert(id);
+    }
+
+    pub fn disconnect(&mut self, id: S) {
+        self.subscribers.remove(&id);
+        self.guards.retain(|(owner, _)| *owner != id);
+    }
+
+    fn check_guards(&self, to: &Route<T>) -> Verdict<T> {
+        for (_, guard) in self.guards.iter().filter(|(_, guard)| guard.applies(&self.current, to)) {
+            match guard.action {
+                GuardAction::Deny => return Verdict::Deny,
+                GuardAction::Redirect(ref target) => return Verdict::Redirect(target.clone()),
+                GuardAction::Confirm(ref message) => {
+                    if !self.route_service.confirm(message) {
+                        return Verdict::Deny;
+                    }
+                }
+            }
+        }
+        Verdict::Allow
+    }
+
+    fn apply_redirects(&self, mut route: Route<T>) -> Route<T> {
+        for _ in 0..MAX_REDIRECTS {
+            match self.redirects.iter().find_map(|redirect| redirect.apply(&route)) {
+                Some(path_segments) => route.path_segments = path_segments,
+                None => return route,
+            }
+        }
+        warn!("Redirect loop detected at {}", route.to_route_string());
+        route
+    }
+
+    /// Runs the redirect table and the guards against a navigation.
+    /// Returns the route that should actually be displayed, or `None` if the navigation was denied.
+    fn resolve(&self, mut route: Route<T>) -> Option<Route<T>> {
+        for _ in 0..MAX_REDIRECTS {
+            route = self.apply_redirects(route);
+            match self.check_guards(&route) {
+                Verdict::Allow => return Some(route),
+                Verdict::Deny => {
+                    info!("Navigation to {} denied by a guard", route.to_route_string());
+                    return None;
+                }
+                Verdict::Redirect(target) => route = target,
+            }
+        }
+        warn!("Too many guard redirects, abandoning navigation to {}", route.to_route_string());
+        None
+    }
+
+    fn broadcast(&self) -> Vec<(S, Route<T>)> {
+        self.subscribers.iter()
+            .map(|sub| (*sub, self.current.clone()))
+            .collect()
+    }
+
+    /// Whether navigating to the route would stay on the current url.
+    fn is_current(&self, route: &Route<T>) -> bool {
+        route.to_route_string() == self.current.to_route_string()
+    }
+
+    /// Navigates to the current url again without adding a history entry.
+    /// Nothing is broadcast unless the route state changed.
+    fn update_current(&mut self, route: Route<T>) -> Vec<(S, Route<T>)> {
+        if route.state == self.current.state {
+            return vec![];
+        }
+        self.route_service.replace_route(&route.to_route_string(), route.state.clone());
+        self.current = route;
+        self.broadcast()
+    }
+
+    /// Handles the user moving through the history, once the browser has moved to the new entry.
+    pub fn browser_navigated(&mut self, state: T) -> Vec<(S, Route<T>)> {
+        info!("Browser navigated");
+        let position = self.route_service.position();
+        if position == self.position {
+            // Back on the displayed entry, after a denied move was undone.
+            return vec![];
+        }
+        let mut route = Route::current_route(&self.route_service);
+        route.state = state;
+        match self.resolve(route) {
+            Some(allowed) => {
+                // The page still shows the entry the user left.
+                self.route_service.save_scroll_position(self.position);
+                // Redirected or denormalized routes replace the entry the browser moved to.
+                let route_string = allowed.to_route_string();
+                if route_string != self.route_service.get_route() {
+                    self.route_service.replace_route(&route_string, allowed.state.clone());
+                }
+                self.current = allowed;
+                self.position = position;
+                let offset = self.route_service.restore_scroll_position(position);
+                // Only this broadcast carries the offset, later ones must not scroll back there.
+                self.broadcast().into_iter()
+                    .map(|(sub, route)| (sub, Route { scroll_offset: Some(offset), ..route }))
+                    .collect()
+            }
+            None => {
+                // The browser has already moved to the denied entry,
+                // so move back to the entry we came from, leaving the history as it was.
+                self.route_service.go(self.position - position);
+                vec![]
+            }
+        }
+    }
+
+    /// Handles a request from a subscriber.
+    pub fn handle(&mut self, msg: Request<T>, who: S) -> Vec<(S, Route<T>)> {
+        info!("Request: {:?}", msg);
+        match msg {
+            Request::ChangeRoute(route) => {
+                if let Some(route) = self.resolve(route) {
+                    if self.is_current(&route) {
+                        return self.update_current(route);
+                    }
+                    let route_string: String = route.to_route_string();
+                    self.route_service.save_scroll_position(self.position);
+                    // set the route, storing its state in the history entry
+                    self.route_service.set_route(&route_string, route.state.clone());
+                    self.position = self.route_service.position();
+                    self.current = route;
+                    // a new page starts at the top, or at the anchor it links to
+                    self.route_service.scroll_to_fragment(self.current.fragment.as_ref().map(String::as_str));
+                    // broadcast it to all listening components, state included
+                    return self.broadcast();
+                }
+            }
+            Request::ChangeRouteNoBroadcast(route) => {
+                if let Some(route) = self.resolve(route) {
+                    if self.is_current(&route) {
+                        self.update_current(route);
+                        return vec![];
+                    }
+                    let route_string: String = route.to_route_string();
+                    self.route_service.save_scroll_position(self.position);
+                    self.route_service.set_route(&route_string, route.state.clone());
+                    self.position = self.route_service.position();
+                    self.current = route;
+                }
+            }
+            Request::ReplaceRoute(route) => {
+                if let Some(route) = self.resolve(route) {
+                    let route_string: String = route.to_route_string();
+                    self.route_service.replace_route(&route_string, route.state.clone());
+                    self.current = route;
+                    return self.broadcast();
+                }
+            }
+            Request::ReplaceState(state) => {
+                self.route_service.replace_route(&self.current.to_route_string(), state.clone());
+                self.current.state = state;
+            }
+            // The browser reports these through a popstate event, where the guards run.
+            Request::Back => self.route_service.back(),
+            Request::Forward => self.route_service.forward(),
+            Request::Go(delta) => self.route_service.go(delta),
+            Request::GetCurrentRoute => {
+                return vec![(who, self.current.clone())];
+            }
+            Request::LoadInitialRoute => {
+                let route = Route::current_route(&self.route_service);
+                if let Some(route) = self.resolve(route) {
+                    // Fix up the url bar without adding an entry to the history.
+                    if route.to_route_string() != self.route_service.get_route() {
+                        self.route_service.replace_route(&route.to_route_string(), route.state.clone());
+                    }
+                    self.current = route;
+                }
+                return vec![(who, self.current.clone())];
+            }
+            Request::RefreshRoute => {
+                return self.broadcast();
+            }
+            Request::SetRedirects(redirects) => {
+                self.redirects = redirects;
+            }
+            Request::AddGuard(guard) => {
+                self.guards.push((who, guard));
+            }
+            Request::ClearGuards => {
+                self.guards.retain(|(owner, _)| *owner != who);
+            }
+        }
+        vec![]
+    }
+}
+
 /// The Router worker holds on to the RouteService singleton and mediates access to it.
 pub struct Router<T>
-    where for <'de> T: JsSerialize + Clone + Debug + TryFrom<Value> + Default + Serialize + Deserialize<'de> + 'static
+    where for <'de> T: JsSerialize + Clone + Debug + TryFrom<Value> + Default + PartialEq + Serialize + Deserialize<'de> + 'static
 {
     link: AgentLink<Router<T>>,
-    route_service: RouteService<T>,
-    /// A list of all entities connected to the router.
-    /// When a route changes, either initiated by the browser or by the app,
-    /// the route change will be broadcast to all listening entities.
-    subscribers: HashSet<HandlerId>,
+    core: RouterCore<T, BrowserHistory<T>, HandlerId>,
+}
+
+impl<T> Router<T>
+    where for <'de> T: JsSerialize + Clone + Debug + TryFrom<Value> + Default + PartialEq + Serialize + Deserialize<'de> + 'static
+{
+    fn respond(&self, responses: Vec<(HandlerId, Route<T>)>) {
+        for (sub, route) in responses {
+            self.link.response(sub, route);
+        }
+    }
 }
 
 impl<T> Agent for Router<T>
-    where for <'de> T: JsSerialize + Clone + Debug + TryFrom<Value> + Default + Serialize + Deserialize<'de> + 'static
+    where for <'de> T: JsSerialize + Clone + Debug + TryFrom<Value> + Default + PartialEq + Serialize + Deserialize<'de> + 'static
 {
     type Reach = Context;
     type Message = Msg<T>;
@@ -119,53 +630,478 @@ impl<T> Agent for Router<T>
 
         Router {
             link,
-            route_service,
-            subscribers: HashSet::new(),
+            core: RouterCore::new(route_service),
         }
     }
 
     fn update(&mut self, msg: Self::Message) {
         match msg {
             Msg::BrowserNavigationRouteChanged((_route_string, state)) => {
-                info!("Browser navigated");
-                let mut route = Route::current_route(&self.route_service);
-                route.state = state;
-                for sub in self.subscribers.iter() {
-                    self.link.response(*sub, route.clone());
-                }
+                let responses = self.core.browser_navigated(state);
+                self.respond(responses);
             }
         }
     }
 
     fn handle(&mut self, msg: Self::Input, who: HandlerId) {
-        info!("Request: {:?}", msg);
-        match msg {
-            Request::ChangeRoute(route) => {
-                let route_string: String = route.to_route_string();
-                // set the route
-                self.route_service.set_route(&route_string, route.state);
-                // get the new route. This will contain a default state object
-                let route = Route::current_route(&self.route_service);
-                // broadcast it to all listening components
-                for sub in self.subscribers.iter() {
-                    self.link.response(*sub, route.clone());
+        let responses = self.core.handle(msg, who);
+        self.respond(responses);
+    }
+
+    fn connected(&mut self, id: HandlerId) {
+        self.core.connect(id);
+    }
+    fn disconnected(&mut self, id: HandlerId) {
+        self.core.disconnect(id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::routes::{self, route, RouteState};
+    use crate::routing::MemoryHistory;
+    use proptest::prelude::*;
+    use std::cell::RefCell;
+    use std::rc::Rc;
+    use yew::Callback;
+
+    type Core = RouterCore<RouteState, MemoryHistory<RouteState>, u32>;
+
+    /// A router core on a memory history, delivering the popstate events of the history
+    /// to the core the way the `Router` agent does.
+    struct TestRouter {
+        core: Core,
+        popped: Rc<RefCell<Vec<(String, RouteState)>>>,
+    }
+
+    impl TestRouter {
+        /// A router opened at the url, with subscribers 1 and 2 connected.
+        fn new(url: &str) -> Self {
+            let popped: Rc<RefCell<Vec<(String, RouteState)>>> = Rc::new(RefCell::new(Vec::new()));
+            let mut route_service = RouteService::with_history(MemoryHistory::new(url));
+            let events = popped.clone();
+            route_service.register_callback(Callback::from(move |entry: (String, RouteState)| events.borrow_mut().push(entry)));
+            let mut core = RouterCore::new(route_service);
+            core.connect(1);
+            core.connect(2);
+            TestRouter { core, popped }
+        }
+
+        /// Handles a request from subscriber 1 and the popstate events it caused,
+        /// including those of the moves the router undoes, returning every route delivered, ordered by subscriber.
+        fn send(&mut self, request: Request<RouteState>) -> Vec<(u32, Route<RouteState>)> {
+            let mut responses = self.core.handle(request, 1);
+            loop {
+                let popped = self.popped.borrow_mut().pop();
+                match popped {
+                    Some((_, state)) => responses.extend(self.core.browser_navigated(state)),
+                    None => break,
                 }
             }
-            Request::ChangeRouteNoBroadcast(route) => {
-                let route_string: String = route.to_route_string();
-                self.route_service.set_route(&route_string, route.state);
-            }
-            Request::GetCurrentRoute => {
-                let route = Route::current_route(&self.route_service);
-                self.link.response(who, route.clone());
-            }
+            responses.sort_by_key(|(sub, _)| *sub);
+            responses
+        }
+
+        fn history(&mut self) -> &mut MemoryHistory<RouteState> {
+            self.core.route_service_mut().history_mut()
+        }
+
+        fn urls(&mut self) -> Vec<String> {
+            self.history().urls().into_iter().map(String::from).collect()
+        }
+
+        fn index(&mut self) -> usize {
+            self.history().index()
         }
     }
 
-    fn connected(&mut self, id: HandlerId) {
-        self.subscribers.insert(id);
+    fn broadcast(route: Route<RouteState>) -> Vec<(u32, Route<RouteState>)> {
+        vec![(1, route.clone()), (2, route)]
     }
-    fn disconnected(&mut self, id: HandlerId) {
-        self.subscribers.remove(&id);
+
+    fn returning(path: &str, x: f64, y: f64) -> Route<RouteState> {
+        Route { scroll_offset: Some((x, y)), ..route(path) }
+    }
+
+    fn guard(condition: GuardCondition, action: GuardAction<RouteState>) -> Request<RouteState> {
+        Request::AddGuard(Guard { condition, action })
+    }
+
+    fn segments(path: &str) -> Vec<String> {
+        path.split('/').filter(|segment| !segment.is_empty()).map(String::from).collect()
+    }
+
+    fn search_state() -> RouteState {
+        RouteState { search_results: Some(vec![]) }
+    }
+
+    #[test]
+    fn change_route_adds_an_entry_and_broadcasts() {
+        let mut router = TestRouter::new("/my_selection");
+
+        let responses = router.send(Request::ChangeRoute(route("/movie_nights")));
+
+        assert_eq!(responses, broadcast(route("/movie_nights")));
+        assert_eq!(router.urls(), vec!["/my_selection", "/movie_nights"]);
+        assert_eq!(router.index(), 1);
+    }
+
+    #[test]
+    fn change_route_to_the_current_url_only_updates_its_state() {
+        let mut router = TestRouter::new("/movies_search");
+
+        assert_eq!(router.send(Request::ChangeRoute(route("/movies_search"))), vec![]);
+
+        let with_state = Route { state: search_state(), ..route("/movies_search") };
+        let responses = router.send(Request::ChangeRoute(with_state.clone()));
+
+        assert_eq!(responses, broadcast(with_state));
+        assert_eq!(router.urls(), vec!["/movies_search"]);
+        assert_eq!(router.index(), 0);
+        assert_eq!(router.core.route_service().get_state(), search_state());
+    }
+
+    #[test]
+    fn change_route_no_broadcast_adds_an_entry_silently() {
+        let mut router = TestRouter::new("/my_selection");
+
+        let responses = router.send(Request::ChangeRouteNoBroadcast(route("/movie_nights")));
+
+        assert_eq!(responses, vec![]);
+        assert_eq!(router.urls(), vec!["/my_selection", "/movie_nights"]);
+        assert_eq!(router.index(), 1);
+    }
+
+    #[test]
+    fn replace_route_replaces_the_current_entry() {
+        let mut router = TestRouter::new("/my_selection");
+        router.send(Request::ChangeRoute(route("/movie_nights")));
+
+        let responses = router.send(Request::ReplaceRoute(route("/movie_nights/42")));
+
+        assert_eq!(responses, broadcast(route("/movie_nights/42")));
+        assert_eq!(router.urls(), vec!["/my_selection", "/movie_nights/42"]);
+        assert_eq!(router.index(), 1);
+    }
+
+    #[test]
+    fn replace_state_keeps_the_url_and_broadcasts_nothing() {
+        let mut router = TestRouter::new("/my_selection");
+        router.send(Request::ChangeRoute(Route { query: Some("q=heat".into()), ..route("/movies_search") }));
+
+        let responses = router.send(Request::ReplaceState(search_state()));
+
+        assert_eq!(responses, vec![]);
+        assert_eq!(router.urls(), vec!["/my_selection", "/movies_search?q=heat"]);
+        assert_eq!(router.index(), 1);
+        assert_eq!(router.core.route_service().get_state(), search_state());
+        assert_eq!(router.core.current().state, search_state());
+    }
+
+    #[test]
+    fn back_and_forward_broadcast_the_entry_with_its_scroll_offset() {
+        let mut router = TestRouter::new("/my_selection");
+        router.history().set_scroll_offset(0.0, 300.0);
+        router.send(Request::ChangeRoute(route("/movie_nights")));
+        router.history().set_scroll_offset(0.0, 50.0);
+
+        let responses = router.send(Request::Back);
+
+        assert_eq!(responses, broadcast(returning("/my_selection", 0.0, 300.0)));
+        assert_eq!(router.urls(), vec!["/my_selection", "/movie_nights"]);
+        assert_eq!(router.index(), 0);
+
+        let responses = router.send(Request::Forward);
+
+        assert_eq!(responses, broadcast(returning("/movie_nights", 0.0, 50.0)));
+        assert_eq!(router.index(), 1);
+        // Later broadcasts do not scroll back to the saved offset.
+        assert_eq!(router.send(Request::RefreshRoute), broadcast(route("/movie_nights")));
+    }
+
+    #[test]
+    fn entries_with_the_same_url_keep_their_own_scroll_offset() {
+        let mut router = TestRouter::new("/movie_nights");
+        router.history().set_scroll_offset(0.0, 100.0);
+        router.send(Request::ChangeRoute(route("/my_selection")));
+        router.history().set_scroll_offset(0.0, 0.0);
+        router.send(Request::ChangeRoute(route("/movie_nights")));
+        router.history().set_scroll_offset(0.0, 500.0);
+        router.send(Request::ChangeRoute(route("/history")));
+
+        assert_eq!(router.send(Request::Back), broadcast(returning("/movie_nights", 0.0, 500.0)));
+        assert_eq!(router.send(Request::Go(-2)), broadcast(returning("/movie_nights", 0.0, 100.0)));
+        assert_eq!(router.urls(), vec!["/movie_nights", "/my_selection", "/movie_nights", "/history"]);
+        assert_eq!(router.index(), 0);
+    }
+
+    #[test]
+    fn back_restores_the_state_of_the_entry() {
+        let mut router = TestRouter::new("/my_selection");
+        router.send(Request::ChangeRoute(Route { state: search_state(), ..route("/movies_search") }));
+        router.send(Request::ChangeRoute(route("/movie_nights")));
+
+        let responses = router.send(Request::Back);
+
+        let expected = Route { state: search_state(), ..returning("/movies_search", 0.0, 0.0) };
+        assert_eq!(responses, broadcast(expected));
+        assert_eq!(router.index(), 1);
+    }
+
+    #[test]
+    fn go_moves_several_entries_and_ignores_moves_past_the_ends() {
+        let mut router = TestRouter::new("/my_selection");
+        router.send(Request::ChangeRoute(route("/movies_search")));
+        router.send(Request::ChangeRoute(route("/movie_nights")));
+
+        let responses = router.send(Request::Go(-2));
+
+        assert_eq!(responses, broadcast(returning("/my_selection", 0.0, 0.0)));
+        assert_eq!(router.urls(), vec!["/my_selection", "/movies_search", "/movie_nights"]);
+        assert_eq!(router.index(), 0);
+
+        assert_eq!(router.send(Request::Go(-1)), vec![]);
+        assert_eq!(router.send(Request::Go(3)), vec![]);
+        assert_eq!(router.index(), 0);
+        assert_eq!(router.core.current(), &route("/my_selection"));
+    }
+
+    #[test]
+    fn change_route_after_back_forgets_the_forward_entries() {
+        let mut router = TestRouter::new("/my_selection");
+        router.send(Request::ChangeRoute(route("/movies_search")));
+        router.send(Request::Back);
+
+        let responses = router.send(Request::ChangeRoute(route("/movie_nights")));
+
+        assert_eq!(responses, broadcast(route("/movie_nights")));
+        assert_eq!(router.urls(), vec!["/my_selection", "/movie_nights"]);
+        assert_eq!(router.index(), 1);
+    }
+
+    #[test]
+    fn deny_guard_cancels_the_navigation() {
+        let mut router = TestRouter::new("/my_selection");
+        router.send(guard(GuardCondition::Entering(segments("/movie_nights")), GuardAction::Deny));
+
+        let responses = router.send(Request::ChangeRoute(route("/movie_nights/42")));
+
+        assert_eq!(responses, vec![]);
+        assert_eq!(router.urls(), vec!["/my_selection"]);
+        assert_eq!(router.index(), 0);
+        assert_eq!(router.core.current(), &route("/my_selection"));
+    }
+
+    #[test]
+    fn redirect_guard_navigates_to_its_target() {
+        let mut router = TestRouter::new("/my_selection");
+        router.send(guard(GuardCondition::Entering(segments("/history")), GuardAction::Redirect(route("/movie_nights"))));
+
+        let responses = router.send(Request::ChangeRoute(route("/history")));
+
+        assert_eq!(responses, broadcast(route("/movie_nights")));
+        assert_eq!(router.urls(), vec!["/my_selection", "/movie_nights"]);
+        assert_eq!(router.index(), 1);
+    }
+
+    #[test]
+    fn confirm_guard_follows_the_answer_of_the_user() {
+        let mut router = TestRouter::new("/movie_nights/new");
+        router.send(guard(GuardCondition::Leaving(segments("/movie_nights/new")), GuardAction::Confirm("Discard?".into())));
+        router.history().set_confirm(false);
+
+        assert_eq!(router.send(Request::ChangeRoute(route("/movie_nights"))), vec![]);
+        assert_eq!(router.urls(), vec!["/movie_nights/new"]);
+        assert_eq!(router.index(), 0);
+
+        router.history().set_confirm(true);
+
+        assert_eq!(router.send(Request::ChangeRoute(route("/movie_nights"))), broadcast(route("/movie_nights")));
+        assert_eq!(router.urls(), vec!["/movie_nights/new", "/movie_nights"]);
+        assert_eq!(router.index(), 1);
+    }
+
+    #[test]
+    fn declined_confirm_guard_puts_the_left_entry_back_after_back() {
+        let mut router = TestRouter::new("/my_selection");
+        router.send(Request::ChangeRoute(route("/movie_nights/new")));
+        router.send(guard(GuardCondition::Leaving(segments("/movie_nights/new")), GuardAction::Confirm("Discard?".into())));
+        router.history().set_confirm(false);
+
+        let responses = router.send(Request::Back);
+
+        assert_eq!(responses, vec![]);
+        assert_eq!(router.urls(), vec!["/my_selection", "/movie_nights/new"]);
+        assert_eq!(router.index(), 1);
+        assert_eq!(router.core.current(), &route("/movie_nights/new"));
+    }
+
+    #[test]
+    fn denied_forward_keeps_the_forward_entries() {
+        let mut router = TestRouter::new("/my_selection");
+        router.send(Request::ChangeRoute(route("/movies_search")));
+        router.send(Request::ChangeRoute(route("/movie_nights")));
+        router.send(Request::Go(-2));
+        router.send(guard(GuardCondition::Entering(segments("/movies_search")), GuardAction::Deny));
+
+        let responses = router.send(Request::Forward);
+
+        assert_eq!(responses, vec![]);
+        assert_eq!(router.urls(), vec!["/my_selection", "/movies_search", "/movie_nights"]);
+        assert_eq!(router.index(), 0);
+        assert_eq!(router.core.current(), &route("/my_selection"));
+    }
+
+    #[test]
+    fn declined_go_keeps_the_history_and_later_moves_still_work() {
+        let mut router = TestRouter::new("/my_selection");
+        router.send(Request::ChangeRoute(route("/movies_search")));
+        router.send(Request::ChangeRoute(route("/movie_nights")));
+        router.send(Request::Go(-2));
+        router.send(guard(GuardCondition::Leaving(segments("/my_selection")), GuardAction::Confirm("Leave?".into())));
+        router.history().set_confirm(false);
+
+        assert_eq!(router.send(Request::Go(2)), vec![]);
+        assert_eq!(router.urls(), vec!["/my_selection", "/movies_search", "/movie_nights"]);
+        assert_eq!(router.index(), 0);
+
+        router.history().set_confirm(true);
+
+        assert_eq!(router.send(Request::Go(2)), broadcast(returning("/movie_nights", 0.0, 0.0)));
+        assert_eq!(router.index(), 2);
+    }
+
+    #[test]
+    fn clear_guards_removes_the_guards_of_the_sender() {
+        let mut router = TestRouter::new("/my_selection");
+        router.send(guard(GuardCondition::Entering(segments("/movie_nights")), GuardAction::Deny));
+        router.send(Request::ClearGuards);
+
+        let responses = router.send(Request::ChangeRoute(route("/movie_nights")));
+
+        assert_eq!(responses, broadcast(route("/movie_nights")));
+    }
+
+    #[test]
+    fn initial_route_applies_the_redirects_without_adding_an_entry() {
+        let mut router = TestRouter::new("/");
+        router.send(Request::SetRedirects(routes::redirects()));
+
+        let responses = router.send(Request::LoadInitialRoute);
+
+        assert_eq!(responses, vec![(1, route("/my_selection"))]);
+        assert_eq!(router.urls(), vec!["/my_selection"]);
+        assert_eq!(router.index(), 0);
+    }
+
+    #[test]
+    fn initial_route_drops_trailing_slashes_and_follows_prefix_redirects() {
+        let mut router = TestRouter::new("/search/");
+        router.send(Request::SetRedirects(routes::redirects()));
+
+        let responses = router.send(Request::LoadInitialRoute);
+
+        assert_eq!(responses, vec![(1, route("/movies_search"))]);
+        assert_eq!(router.urls(), vec!["/movies_search"]);
+        assert_eq!(router.index(), 0);
+    }
+
+    #[test]
+    fn get_current_route_changes_nothing() {
+        let mut router = TestRouter::new("/movie_nights/");
+        router.send(Request::SetRedirects(routes::redirects()));
+        router.send(guard(GuardCondition::Entering(segments("/movie_nights")), GuardAction::Deny));
+
+        let responses = router.send(Request::GetCurrentRoute);
+
+        assert_eq!(responses, vec![(1, route("/movie_nights"))]);
+        assert_eq!(router.urls(), vec!["/movie_nights/"]);
+        assert_eq!(router.index(), 0);
+    }
+
+    #[test]
+    fn redirects_apply_to_navigations() {
+        let mut router = TestRouter::new("/movie_nights");
+        router.send(Request::SetRedirects(routes::redirects()));
+
+        let responses = router.send(Request::ChangeRoute(route("/selection/")));
+
+        assert_eq!(responses, broadcast(route("/my_selection")));
+        assert_eq!(router.urls(), vec!["/movie_nights", "/my_selection"]);
+        assert_eq!(router.index(), 1);
+    }
+
+    #[test]
+    fn back_to_an_old_url_replaces_it_with_the_redirected_one() {
+        let mut router = TestRouter::new("/search");
+        router.send(Request::SetRedirects(routes::redirects()));
+        router.send(Request::ChangeRoute(route("/movie_nights")));
+
+        let responses = router.send(Request::Back);
+
+        assert_eq!(responses, broadcast(returning("/movies_search", 0.0, 0.0)));
+        assert_eq!(router.urls(), vec!["/movies_search", "/movie_nights"]);
+        assert_eq!(router.index(), 0);
+    }
+
+    /// Path segments that survive a trip through the url: any text, but not ending with an empty segment.
+    fn path_segments() -> impl Strategy<Value = Vec<String>> {
+        prop::collection::vec(".*", 0..5)
+            .prop_filter("a trailing empty segment is dropped", |segments| {
+                segments.last().map_or(true, |segment| !segment.is_empty())
+            })
+    }
+
+    proptest! {
+        #[test]
+        fn segments_decode_to_what_was_encoded(segment in ".*") {
+            prop_assert_eq!(percent_decode(&encode_segment(&segment)), segment);
+        }
+
+        #[test]
+        fn fragments_decode_to_what_was_encoded(fragment in ".*") {
+            prop_assert_eq!(percent_decode(&encode_fragment(&fragment)), fragment);
+        }
+
+        #[test]
+        fn encoded_segments_keep_only_unreserved_characters(segment in ".*") {
+            let encoded = encode_segment(&segment);
+            prop_assert!(!encoded.contains(|c| c == '/' || c == '?' || c == '#' || c == ' ' || !c.is_ascii()));
+        }
+
+        #[test]
+        fn routes_read_back_from_their_url(
+            path_segments in path_segments(),
+            fragment in proptest::option::of(".+"),
+        ) {
+            let route: Route<RouteState> = Route { path_segments, fragment, ..Route::default() };
+            let route_service = RouteService::with_history(MemoryHistory::new(&route.to_route_string()));
+
+            prop_assert_eq!(Route::current_route(&route_service), route);
+        }
+    }
+
+    #[test]
+    fn routes_read_back_reserved_and_non_ascii_characters() {
+        let path_segments = vec!["a/b".to_string(), "#1".into(), "100%".into(), "movie night".into(), "été ☕".into()];
+        let route: Route<RouteState> = Route {
+            path_segments,
+            fragment: Some("movie 42#?/%".into()),
+            ..Route::default()
+        };
+        let url = route.to_route_string();
+        let route_service = RouteService::with_history(MemoryHistory::new(&url));
+
+        assert_eq!(url, "/a%2Fb/%231/100%25/movie%20night/%C3%A9t%C3%A9%20%E2%98%95#movie%2042%23?/%25");
+        assert_eq!(Route::current_route(&route_service), route);
+    }
+
+    #[test]
+    fn trailing_empty_segments_are_dropped() {
+        let route: Route<RouteState> = Route { path_segments: vec!["a".into(), "".into()], ..Route::default() };
+        let route_service = RouteService::with_history(MemoryHistory::new(&route.to_route_string()));
+
+        assert_eq!(Route::current_route(&route_service).path_segments, vec!["a"]);
     }
 }
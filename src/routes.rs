@@ -0,0 +1,95 @@
+//! The application's route table.
+
+use serde_derive::{Deserialize, Serialize};
+use stdweb::web::document;
+
+use crate::router::{Mount, Redirect, Route, Router};
+use crate::services::froovie_service::MovieSearch;
+
+/// State stored in each history entry, handed back to the components when the user returns to it.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct RouteState {
+    /// Results shown by the movie search, so going back to it does not hit the backend again.
+    pub search_results: Option<Vec<MovieSearch>>,
+}
+
+js_serializable!(RouteState);
+js_deserializable!(RouteState);
+
+/// The router shared by every component of the app.
+pub type AppRouter = Router<RouteState>;
+
+/// The sections of the app as (path, name), linked from the navigation menu and the not found page.
+pub const SECTIONS: &[(&str, &str)] = &[
+    ("/my_selection", "My Movies"),
+    ("/movies_search", "Search a Movie"),
+    ("/movie_nights", "Movie Nights"),
+    ("/history", "Watch History"),
+];
+
+/// A route to an absolute path of the app.
+pub fn route(path: &str) -> Route<RouteState> {
+    Mount::default().route(path)
+}
+
+/// The section whose path is closest to `path`, if it is close enough to be what the user meant.
+pub fn closest_section(path: &str) -> Option<(&'static str, &'static str)> {
+    SECTIONS.iter()
+        .map(|&(section, name)| (edit_distance(path, section), section, name))
+        .filter(|&(distance, section, _)| distance <= section.len() / 2)
+        .min_by_key(|&(distance, _, _)| distance)
+        .map(|(_, section, name)| (section, name))
+}
+
+/// The Levenshtein distance between two strings, counted in characters.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, a_char) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, b_char) in b.iter().enumerate() {
+            let substitution = previous[j] + if a_char == *b_char { 0 } else { 1 };
+            let deletion = previous[j + 1] + 1;
+            let insertion = current[j] + 1;
+            current.push(substitution.min(deletion).min(insertion));
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
+/// The absolute url of a route, to use outside of the app.
+pub fn absolute_url(route: &Route<RouteState>) -> String {
+    let origin = js! { return window.location.origin; };
+    format!("{}{}", origin.into_string().unwrap_or_default(), route.to_route_string())
+}
+
+/// Copies the absolute url of a route to the clipboard, to share it.
+/// Browsers without clipboard access show the url for the user to copy instead.
+pub fn copy_link(route: &Route<RouteState>) {
+    js! { @(no_return)
+        var url = @{absolute_url(route)};
+        var ask = function() { window.prompt("Copy this link", url); };
+        if (navigator.clipboard) {
+            navigator.clipboard.writeText(url).catch(ask);
+        } else {
+            ask();
+        }
+    }
+}
+
+/// Sets the title of the browser tab, so history entries and bookmarks can be told apart.
+pub fn set_title(page: &str) {
+    document().set_title(&format!("{} - Froovie", page));
+}
+
+/// Redirects applied by the router to every navigation, including the first page load.
+pub fn redirects() -> Vec<Redirect> {
+    vec![
+        // The start screen.
+        Redirect::exact("/", "/my_selection"),
+        // Section paths from before they were renamed, still found in old bookmarks.
+        Redirect::prefix("/selection", "/my_selection"),
+        Redirect::prefix("/search", "/movies_search"),
+    ]
+}
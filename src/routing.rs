@@ -8,99 +8,498 @@ use stdweb::web::EventListenerHandle;
 use stdweb::web::event::PopStateEvent;
 use stdweb::web::IEventTarget;
 use stdweb::JsSerialize;
-use stdweb::unstable::TryFrom;
+use stdweb::unstable::{TryFrom, TryInto};
 use yew::callback::Callback;
+use log::warn;
 
+use std::collections::HashMap;
 use std::marker::PhantomData;
 
 
-/// A service that facilitates manipulation of the browser's URL bar and responding to browser
-/// 'forward' and 'back' events.
+/// Scrolls to the element whose id is the fragment, or to the top if there is none,
+/// once the views have rendered.
+pub fn scroll_to_fragment(fragment: Option<&str>) {
+    js! { @(no_return)
+        var fragment = @{fragment};
+        setTimeout(function() {
+            var element = fragment ? document.getElementById(fragment) : null;
+            if (element) {
+                element.scrollIntoView();
+            } else {
+                window.scrollTo(0, 0);
+            }
+        }, 0);
+    }
+}
+
+/// Scrolls the page to the given offset, once the views have rendered.
+pub fn scroll_to(x: f64, y: f64) {
+    js! { @(no_return)
+        setTimeout(function() { window.scrollTo(@{x}, @{y}); }, 0);
+    }
+}
+
+/// The session history and the page displaying it, as seen by the route service.
 ///
-/// The `T` determines what route state can be stored in the route service.
-pub struct RouteService<T> {
+/// `BrowserHistory` drives the real browser, `MemoryHistory` keeps everything in memory
+/// so the routing logic can run outside of a browser.
+pub trait HistoryBackend<T> {
+    /// Calls the callback when the user moves through the history, e.g. with the back button.
+    fn register_callback(&mut self, callback: Callback<(String, T)>);
+
+    /// Adds a history entry for the url and makes it the current one.
+    fn push_state(&mut self, state: T, url: &str);
+
+    /// Replaces the current history entry.
+    fn replace_state(&mut self, state: T, url: &str);
+
+    /// Moves through the history by `delta` entries, backward if negative.
+    fn go(&mut self, delta: i32);
+
+    /// The path name of the current url, starting with a '/'.
+    fn pathname(&self) -> String;
+
+    /// The query string of the current url, starting with a '?' unless it is empty.
+    fn search(&self) -> String;
+
+    /// The fragment of the current url, starting with a '#' unless it is empty.
+    fn hash(&self) -> String;
+
+    /// The state object stored with the current history entry.
+    fn state(&self) -> T;
+
+    /// The position of the current entry in the session history,
+    /// counting from the entry the app was first opened at.
+    /// It tells how far the user moved when they go back or forward.
+    fn position(&self) -> i32;
+
+    /// Asks the user to confirm something and returns whether they accepted.
+    fn confirm(&self, message: &str) -> bool;
+
+    /// The current scroll offset of the page.
+    fn scroll_offset(&self) -> (f64, f64);
+
+    /// Scrolls the page, once the views have rendered the current route.
+    fn scroll_to(&self, x: f64, y: f64);
+
+    /// Scrolls to the element named by the fragment, or to the top if there is none,
+    /// once the views have rendered the current route.
+    fn scroll_to_fragment(&self, fragment: Option<&str>);
+}
+
+/// The browser's History and Location APIs.
+pub struct BrowserHistory<T> {
     history: History,
     location: Location,
     event_listener: Option<EventListenerHandle>,
     phantom_data: PhantomData<T>
 }
 
-
-impl <T> RouteService<T>
-    where T: JsSerialize + Clone + TryFrom<Value> + 'static
+impl <T> BrowserHistory<T>
+    where T: JsSerialize + TryFrom<Value> + Default + 'static
 {
-    /// Creates the route service.
-    pub fn new() -> RouteService<T> {
+    pub fn new() -> BrowserHistory<T> {
         let location = window().location().expect("browser does not support location API");
-        RouteService {
-            history: window().history(),
+        let history = window().history();
+        // The browser restores the scroll position before the new view is rendered, so we do it ourselves.
+        js! { @(no_return)
+            if ("scrollRestoration" in @{&history}) {
+                @{&history}.scrollRestoration = "manual";
+            }
+        }
+        BrowserHistory {
+            history,
             location,
             event_listener: None,
             phantom_data: PhantomData
         }
     }
 
-    /// Registers a callback to the route service.
-    /// Callbacks will be called when the History API experiences a change such as
-    /// popping a state off of its stack when the forward or back buttons are pressed.
-    pub fn register_callback(&mut self, callback: Callback<(String, T)>) {
+    /// Reads the route state of an entry, stored in the history as `{ route, position }`.
+    /// Entries that were not created by the route service, like the first page loaded, hold no state
+    /// and get the default one.
+    fn state_from_value(value: Value) -> T {
+        let value = js! {
+            var entry = @{value};
+            return entry ? entry.route : null;
+        };
+        match value {
+            Value::Undefined | Value::Null => T::default(),
+            value => T::try_from(value).unwrap_or_else(|_| {
+                warn!("Unreadable state in the history, using the default route state.");
+                T::default()
+            })
+        }
+    }
+
+    fn get_route_from_location(location: &Location) -> String {
+        let path = location.pathname().unwrap();
+        let query = location.search().unwrap();
+        let fragment = location.hash().unwrap();
+        format!("{path}{query}{fragment}",
+            path=path,
+            query=query,
+            fragment=fragment)
+    }
+}
+
+impl <T> HistoryBackend<T> for BrowserHistory<T>
+    where T: JsSerialize + TryFrom<Value> + Default + 'static
+{
+    fn register_callback(&mut self, callback: Callback<(String, T)>) {
         self.event_listener = Some(window().add_event_listener(
             move |event: PopStateEvent| {
-                let state_value: Value = event.state();
-
-                if let Ok(state) = T::try_from(state_value) {
-                    let location: Location = window().location().unwrap();
-                    let route: String = Self::get_route_from_location(&location);
+                let state: T = Self::state_from_value(event.state());
+                let location: Location = window().location().unwrap();
+                let route: String = Self::get_route_from_location(&location);
 
-                    callback.emit((route.clone(), state.clone()))
-                } else {
-                    eprintln!("Nothing farther back in history, not calling routing callback.");
-                }
+                callback.emit((route, state))
             },
         ));
     }
 
+    fn push_state(&mut self, state: T, url: &str) {
+        let position = self.position() + 1;
+        js! { @(no_return)
+            @{&self.history}.pushState({ route: @{state}, position: @{position} }, "", @{url});
+        }
+    }
+
+    fn replace_state(&mut self, state: T, url: &str) {
+        let position = self.position();
+        js! { @(no_return)
+            @{&self.history}.replaceState({ route: @{state}, position: @{position} }, "", @{url});
+        }
+    }
+
+    fn go(&mut self, delta: i32) {
+        self.history.go(delta).unwrap();
+    }
+
+    fn pathname(&self) -> String {
+        self.location.pathname().unwrap()
+    }
+
+    fn search(&self) -> String {
+        self.location.search().unwrap()
+    }
+
+    fn hash(&self) -> String {
+        self.location.hash().unwrap()
+    }
+
+    fn state(&self) -> T {
+        let state = js! {
+            return @{&self.history}.state;
+        };
+        Self::state_from_value(state)
+    }
+
+    fn position(&self) -> i32 {
+        let position = js! {
+            var entry = @{&self.history}.state;
+            return entry && typeof entry.position === "number" ? entry.position : 0;
+        };
+        position.try_into().unwrap_or(0)
+    }
+
+    fn confirm(&self, message: &str) -> bool {
+        let confirmed = js! {
+            return window.confirm(@{message});
+        };
+        confirmed.try_into().unwrap_or(false)
+    }
+
+    fn scroll_offset(&self) -> (f64, f64) {
+        let x: f64 = js! { return window.pageXOffset; }.try_into().unwrap_or(0.0);
+        let y: f64 = js! { return window.pageYOffset; }.try_into().unwrap_or(0.0);
+        (x, y)
+    }
+
+    fn scroll_to(&self, x: f64, y: f64) {
+        scroll_to(x, y);
+    }
+
+    fn scroll_to_fragment(&self, fragment: Option<&str>) {
+        scroll_to_fragment(fragment);
+    }
+}
+
+/// A history kept in memory, for running the router without a browser.
+///
+/// Moving through it with `go` reports the new entry to the registered callback right away,
+/// like the browser's popstate event. Confirmations get the answer set with `set_confirm`.
+pub struct MemoryHistory<T> {
+    /// The urls and states of the entries, oldest first.
+    entries: Vec<(String, T)>,
+    index: usize,
+    callback: Option<Callback<(String, T)>>,
+    confirm: bool,
+    scroll_offset: (f64, f64),
+}
+
+impl <T> MemoryHistory<T>
+    where T: Clone + Default
+{
+    /// Creates a history holding a single entry for the url, like a freshly opened tab.
+    pub fn new(url: &str) -> MemoryHistory<T> {
+        MemoryHistory {
+            entries: vec![(url.to_string(), T::default())],
+            index: 0,
+            callback: None,
+            confirm: true,
+            scroll_offset: (0.0, 0.0),
+        }
+    }
+
+    /// The urls of every entry, oldest first.
+    pub fn urls(&self) -> Vec<&str> {
+        self.entries.iter().map(|(url, _)| url.as_str()).collect()
+    }
+
+    /// The position of the current entry in `urls`.
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    /// Sets the answer given to confirmation dialogs.
+    pub fn set_confirm(&mut self, confirm: bool) {
+        self.confirm = confirm;
+    }
+
+    /// Sets the scroll offset of the page, as if the user scrolled.
+    pub fn set_scroll_offset(&mut self, x: f64, y: f64) {
+        self.scroll_offset = (x, y);
+    }
+
+    fn url(&self) -> &str {
+        &self.entries[self.index].0
+    }
+
+    /// Splits the current url at the first '?' and '#', keeping the separators.
+    fn url_parts(&self) -> (&str, &str, &str) {
+        let url = self.url();
+        let (rest, hash) = match url.find('#') {
+            Some(position) => url.split_at(position),
+            None => (url, ""),
+        };
+        let (pathname, search) = match rest.find('?') {
+            Some(position) => rest.split_at(position),
+            None => (rest, ""),
+        };
+        (pathname, search, hash)
+    }
+}
+
+impl <T> HistoryBackend<T> for MemoryHistory<T>
+    where T: Clone + Default
+{
+    fn register_callback(&mut self, callback: Callback<(String, T)>) {
+        self.callback = Some(callback);
+    }
+
+    fn push_state(&mut self, state: T, url: &str) {
+        // like the browser, forget the entries we went back from
+        self.entries.truncate(self.index + 1);
+        self.entries.push((url.to_string(), state));
+        self.index = self.entries.len() - 1;
+    }
+
+    fn replace_state(&mut self, state: T, url: &str) {
+        self.entries[self.index] = (url.to_string(), state);
+    }
+
+    fn go(&mut self, delta: i32) {
+        let target = self.index as i64 + i64::from(delta);
+        // the browser ignores moves past either end of the history
+        if delta == 0 || target < 0 || target >= self.entries.len() as i64 {
+            return;
+        }
+        self.index = target as usize;
+        if let Some(ref callback) = self.callback {
+            let (url, state) = self.entries[self.index].clone();
+            callback.emit((url, state));
+        }
+    }
+
+    fn pathname(&self) -> String {
+        self.url_parts().0.to_string()
+    }
+
+    fn search(&self) -> String {
+        self.url_parts().1.to_string()
+    }
+
+    fn hash(&self) -> String {
+        self.url_parts().2.to_string()
+    }
+
+    fn state(&self) -> T {
+        self.entries[self.index].1.clone()
+    }
+
+    fn position(&self) -> i32 {
+        self.index as i32
+    }
+
+    fn confirm(&self, _message: &str) -> bool {
+        self.confirm
+    }
+
+    fn scroll_offset(&self) -> (f64, f64) {
+        self.scroll_offset
+    }
+
+    fn scroll_to(&self, _x: f64, _y: f64) {}
+
+    fn scroll_to_fragment(&self, _fragment: Option<&str>) {}
+}
+
+
+/// A service that facilitates manipulation of the browser's URL bar and responding to browser
+/// 'forward' and 'back' events.
+///
+/// The `T` determines what route state can be stored in the route service,
+/// and `H` the history it works on, the browser's unless stated otherwise.
+pub struct RouteService<T, H = BrowserHistory<T>> {
+    history: H,
+    /// Scroll offsets of the pages the user left, keyed by the position of their history entry,
+    /// so entries with the same url keep their own offset.
+    scroll_positions: HashMap<i32, (f64, f64)>,
+    phantom_data: PhantomData<T>
+}
+
+
+impl <T> RouteService<T>
+    where T: JsSerialize + Clone + TryFrom<Value> + Default + 'static
+{
+    /// Creates the route service for the browser's history.
+    pub fn new() -> RouteService<T> {
+        RouteService::with_history(BrowserHistory::new())
+    }
+}
+
+impl <T, H> RouteService<T, H>
+    where T: Clone,
+          H: HistoryBackend<T>
+{
+    /// Creates the route service for the given history.
+    pub fn with_history(history: H) -> RouteService<T, H> {
+        RouteService {
+            history,
+            scroll_positions: HashMap::new(),
+            phantom_data: PhantomData
+        }
+    }
+
+    /// The history the service works on.
+    pub fn history(&self) -> &H {
+        &self.history
+    }
+
+    /// The history the service works on, e.g. to simulate the user in tests.
+    pub fn history_mut(&mut self) -> &mut H {
+        &mut self.history
+    }
+
+    /// Registers a callback to the route service.
+    /// Callbacks will be called when the History API experiences a change such as
+    /// popping a state off of its stack when the forward or back buttons are pressed.
+    pub fn register_callback(&mut self, callback: Callback<(String, T)>) {
+        self.history.register_callback(callback);
+    }
+
 
     /// Sets the browser's url bar to contain the provided route,
     /// and creates a history entry that can be navigated via the forward and back buttons.
     /// The route should be a relative path that starts with a '/'.
     /// A state object be stored with the url.
     pub fn set_route(&mut self, route: &str, state: T) {
+        self.history.push_state(state, route);
+        // The entries the new one replaced in the history can not be returned to.
+        let position = self.history.position();
+        self.scroll_positions.retain(|&saved, _| saved < position);
+    }
 
-        self.history.push_state(
-            state,
-            "",
-            Some(route),
-        );
+    /// Replaces the current history entry with the provided route,
+    /// without creating a new entry for the forward and back buttons.
+    pub fn replace_route(&mut self, route: &str, state: T) {
+        self.history.replace_state(state, route);
     }
 
-    fn get_route_from_location(location: &Location) -> String {
-        let path = location.pathname().unwrap();
-        let query = location.search().unwrap();
-        let fragment = location.hash().unwrap();
-        format!("{path}{query}{fragment}",
-            path=path,
-            query=query,
-            fragment=fragment)
+    /// Moves through the history by `delta` entries, like the browser's back and forward buttons.
+    /// The route change is reported to the registered callback once the browser has moved.
+    pub fn go(&mut self, delta: i32) {
+        self.history.go(delta);
+    }
+
+    /// The position of the current history entry, see `HistoryBackend::position`.
+    pub fn position(&self) -> i32 {
+        self.history.position()
+    }
+
+    /// Goes one entry back in the history.
+    pub fn back(&mut self) {
+        self.history.go(-1);
+    }
+
+    /// Goes one entry forward in the history.
+    pub fn forward(&mut self) {
+        self.history.go(1);
+    }
+
+    /// Remembers the scroll offset of the page, to restore it when the user comes back
+    /// to the history entry at `position`.
+    pub fn save_scroll_position(&mut self, position: i32) {
+        let offset = self.history.scroll_offset();
+        self.scroll_positions.insert(position, offset);
+    }
+
+    /// Scrolls back to where the user left the history entry at `position`,
+    /// or to the top if they never left it, and returns that offset.
+    /// This is deferred until the views have rendered the route.
+    pub fn restore_scroll_position(&self, position: i32) -> (f64, f64) {
+        let (x, y) = self.scroll_positions.get(&position).cloned().unwrap_or((0.0, 0.0));
+        self.history.scroll_to(x, y);
+        (x, y)
+    }
+
+    /// Scrolls to the element named by the fragment, or to the top if there is none.
+    /// This is deferred until the views have rendered the route.
+    pub fn scroll_to_fragment(&self, fragment: Option<&str>) {
+        self.history.scroll_to_fragment(fragment);
+    }
+
+    /// Shows a blocking confirmation dialog and returns whether the user accepted it.
+    pub fn confirm(&self, message: &str) -> bool {
+        self.history.confirm(message)
+    }
+
+    /// Gets the state object stored with the current history entry.
+    pub fn get_state(&self) -> T {
+        self.history.state()
     }
 
     /// Gets the concatenated path, query, and fragment strings
     pub fn get_route(&self) -> String {
-        Self::get_route_from_location(&self.location)
+        format!("{path}{query}{fragment}",
+            path=self.get_path(),
+            query=self.get_query(),
+            fragment=self.get_fragment())
     }
 
     /// Gets the path name of the current url.
     pub fn get_path(&self) -> String {
-        self.location.pathname().unwrap()
+        self.history.pathname()
     }
 
     /// Gets the query string of the current url.
     pub fn get_query(&self) -> String {
-        self.location.search().unwrap()
+        self.history.search()
     }
 
     /// Gets the fragment of the current url.
     pub fn get_fragment(&self) -> String {
-        self.location.hash().unwrap()
+        self.history.hash()
     }
-}
\ No newline at end of file
+}
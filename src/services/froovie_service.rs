@@ -1,27 +1,36 @@
 use failure::{format_err, Error};
+use serde::de::DeserializeOwned;
 use serde_derive::Deserialize;
 use serde_derive::Serialize;
 use yew::callback::Callback;
 use yew::services::fetch::StatusCode;
-use yew::format::{Json, Nothing};
+use yew::format::{Json, Nothing, Text};
 use yew::services::fetch::{FetchService, FetchTask, Request, Response};
 
 const BACKEND: &str = "http://localhost:6767";
 
-#[derive(Deserialize, Debug, Clone)]
+/// The user the app acts as until someone signs up in the browser, see `session`.
+pub const DEFAULT_USER_ID: i32 = 1;
+
+#[derive(Deserialize, Debug, Clone, Default, PartialEq)]
 pub struct Selections {
     pub movies: Vec<Movie>,
 }
 
-#[derive(Deserialize, Debug, Clone)]
+#[derive(Deserialize, Debug, Clone, PartialEq)]
 #[serde(rename_all = "snake_case")]
 pub struct Movie {
     pub id: i32,
+    /// The Movie Database id, shared by every user's selection of the movie.
+    pub moviedb_id: i32,
     pub title: String,
     pub description: String,
+    /// In minutes, when known.
+    #[serde(default)]
+    pub runtime: Option<u32>,
 }
 
-#[derive(Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 #[serde(rename_all = "snake_case")]
 pub struct MovieSearch {
     pub moviedb_id: i32,
@@ -43,6 +52,255 @@ pub struct UserSelection {
 
 }
 
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+#[serde(rename_all = "snake_case")]
+pub struct MovieNight {
+    pub id: i32,
+    pub title: String,
+    /// Local date and time, as `YYYY-MM-DDTHH:MM`.
+    pub date: String,
+    pub location: String,
+    pub host_id: i32,
+    pub participants: Vec<i32>,
+    pub cancelled: bool,
+    /// Moviedb ids of the candidates the host keeps in the pool.
+    #[serde(default)]
+    pub pinned: Vec<i32>,
+    /// Moviedb ids of the candidates the host took out of the pool.
+    #[serde(default)]
+    pub excluded: Vec<i32>,
+    #[serde(default)]
+    pub voting_method: VotingMethod,
+    /// How many candidates each participant can veto.
+    #[serde(default)]
+    pub vetoes_per_participant: u32,
+    /// Whether the vetoes are shown without who cast them.
+    #[serde(default)]
+    pub anonymous_vetoes: bool,
+    #[serde(default)]
+    pub vetoes: Vec<Veto>,
+    /// The answers of the participants who told whether they are coming.
+    #[serde(default)]
+    pub attendances: Vec<Attendance>,
+    /// Whether the movie was watched, and recorded in the watch history.
+    #[serde(default)]
+    pub watched: bool,
+}
+
+impl MovieNight {
+    /// The answer of a participant, `None` if they did not answer yet.
+    pub fn rsvp(&self, participant: i32) -> Option<Rsvp> {
+        self.attendances.iter()
+            .find(|attendance| attendance.participant == participant)
+            .map(|attendance| attendance.rsvp)
+    }
+
+    /// The participants who answered that they are going.
+    pub fn going(&self) -> Vec<i32> {
+        self.participants.iter()
+            .cloned()
+            .filter(|participant| self.rsvp(*participant) == Some(Rsvp::Going))
+            .collect()
+    }
+}
+
+/// Whether a participant is coming to a movie night.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum Rsvp {
+    Going,
+    Maybe,
+    NotGoing,
+}
+
+impl Rsvp {
+    pub const ALL: [Rsvp; 3] = [Rsvp::Going, Rsvp::Maybe, Rsvp::NotGoing];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Rsvp::Going => "Going",
+            Rsvp::Maybe => "Maybe",
+            Rsvp::NotGoing => "Not going",
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub struct Attendance {
+    pub participant: i32,
+    pub rsvp: Rsvp,
+}
+
+/// A candidate vetoed by a participant, taking it out of the pool before the vote.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub struct Veto {
+    pub moviedb_id: i32,
+    /// Who vetoed the candidate, `None` when the vetoes are anonymous and it is not the current user.
+    pub voter: Option<i32>,
+}
+
+/// How the participants of a movie night vote, picked by the host.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum VotingMethod {
+    /// Rank the candidates, counted with instant-runoff voting.
+    Ranked,
+    /// Approve every candidate you would watch.
+    Approval,
+    /// Give every candidate from 0 to `MAX_SCORE` stars.
+    Score,
+    /// Choose one candidate.
+    Plurality,
+}
+
+impl VotingMethod {
+    pub const ALL: [VotingMethod; 4] = [VotingMethod::Ranked, VotingMethod::Approval, VotingMethod::Score, VotingMethod::Plurality];
+
+    pub fn description(self) -> &'static str {
+        match self {
+            VotingMethod::Ranked => "Rank the candidates",
+            VotingMethod::Approval => "Tick every candidate you would watch",
+            VotingMethod::Score => "Give 0 to 5 stars to each candidate",
+            VotingMethod::Plurality => "Choose one candidate",
+        }
+    }
+}
+
+impl Default for VotingMethod {
+    fn default() -> Self {
+        VotingMethod::Ranked
+    }
+}
+
+/// The most stars a candidate can get from a ballot with score voting.
+pub const MAX_SCORE: u8 = 5;
+
+/// The host's choices about the candidate pool of a movie night.
+#[derive(Serialize, Debug, Clone, PartialEq, Default)]
+pub struct CandidateChoices {
+    pub pinned: Vec<i32>,
+    pub excluded: Vec<i32>,
+}
+
+/// A shareable link letting anyone who opens it join a movie night, found at `/join/:token`.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+#[serde(rename_all = "snake_case")]
+pub struct Invite {
+    pub token: String,
+    pub movie_night_id: i32,
+    /// Local date and time, as `YYYY-MM-DDTHH:MM`, after which the invite cannot be used.
+    pub expires_at: String,
+    /// How many people can join with the invite.
+    pub max_uses: u32,
+    pub uses: u32,
+}
+
+impl Invite {
+    /// Whether someone can still join with the invite, `now` being formatted like `expires_at`.
+    pub fn is_usable(&self, now: &str) -> bool {
+        self.uses < self.max_uses && now < self.expires_at.as_str()
+    }
+}
+
+/// What the host fills in to create an invite.
+#[derive(Serialize, Debug, Clone, PartialEq, Default)]
+pub struct InviteForm {
+    pub expires_at: String,
+    pub max_uses: u32,
+}
+
+/// An invite, with the movie night it is for.
+#[derive(Deserialize, Debug, Clone, PartialEq, Default)]
+#[serde(rename_all = "snake_case")]
+pub struct InviteDetails {
+    pub invite: Invite,
+    pub movie_night: MovieNight,
+}
+
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub struct User {
+    pub id: i32,
+    pub name: String,
+}
+
+#[derive(Serialize, Debug)]
+pub struct SignUp<'a> {
+    pub name: &'a str,
+}
+
+#[derive(Serialize, Debug)]
+struct JoinRequest {
+    user_id: i32,
+}
+
+/// What the host tells once a movie night's movie was watched.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct WatchedForm {
+    pub moviedb_id: i32,
+    pub date: String,
+    pub attendees: Vec<i32>,
+    /// Whether to remove the movie from the attendees' selections.
+    pub remove_from_selections: bool,
+}
+
+/// A movie the group watched, in the watch history.
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub struct WatchedMovie {
+    pub movie_night_id: i32,
+    /// The title of the movie night.
+    pub title: String,
+    pub movie: Movie,
+    /// Local date and time, as `YYYY-MM-DDTHH:MM`.
+    pub date: String,
+    pub attendees: Vec<i32>,
+}
+
+/// A participant's vote on a movie night.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+#[serde(rename_all = "snake_case")]
+pub struct Ballot {
+    pub voter: i32,
+    /// Moviedb ids of the candidates, from the most to the least preferred, with ranked voting.
+    #[serde(default)]
+    pub ranking: Vec<i32>,
+    /// Moviedb ids of the approved candidates, with approval voting.
+    #[serde(default)]
+    pub approved: Vec<i32>,
+    /// Stars per candidate moviedb id, with score voting.
+    #[serde(default)]
+    pub scores: Vec<(i32, u8)>,
+    /// Moviedb id of the chosen candidate, with plurality voting.
+    #[serde(default)]
+    pub choice: Option<i32>,
+}
+
+/// What the host fills in to create or edit a movie night.
+#[derive(Serialize, Debug, Clone, PartialEq, Default)]
+pub struct MovieNightForm {
+    pub title: String,
+    pub date: String,
+    pub location: String,
+    pub host_id: i32,
+    pub participants: Vec<i32>,
+    pub voting_method: VotingMethod,
+    pub vetoes_per_participant: u32,
+    pub anonymous_vetoes: bool,
+}
+
+/// The url of a user's iCalendar feed of upcoming movie nights, for calendar apps to subscribe to.
+///
+/// The backend serves `GET /users/:id/movie_nights.ics` as `text/calendar`,
+/// with the same content `ics::upcoming` builds for the user, the event urls linking to the app.
+/// The url uses the webcal scheme, so calendar apps subscribe to it rather than import it once:
+/// `http://` becomes `webcal://` and `https://` becomes `webcals://`.
+pub fn movie_nights_feed_url(user_id: i32) -> String {
+    format!("{}/users/{}/movie_nights.ics", BACKEND.replacen("http", "webcal", 1), user_id)
+}
+
 #[derive(Default)]
 pub struct FroovieService {
     web: FetchService,
@@ -123,4 +381,202 @@ impl FroovieService {
         let request = Request::post(url.as_str()).body(Json(&query)).unwrap();
         self.web.fetch(request, handler.into())
     }
+
+    pub fn create_movie_night(
+        &mut self,
+        form: &MovieNightForm,
+        callback: Callback<Result<MovieNight, Error>>,
+    ) -> FetchTask {
+        let url = format!("{}/movie_nights", BACKEND);
+        let request = Request::post(url.as_str()).body(Json(form)).unwrap();
+        self.fetch_json(request, callback, "creating movie night")
+    }
+
+    pub fn get_movie_night(
+        &mut self,
+        id: &str,
+        callback: Callback<Result<MovieNight, Error>>,
+    ) -> FetchTask {
+        let url = format!("{}/movie_nights/{}", BACKEND, id);
+        let request = Request::get(url.as_str()).body(Nothing).unwrap();
+        self.fetch_json(request, callback, "getting movie night")
+    }
+
+    pub fn list_movie_nights(
+        &mut self,
+        callback: Callback<Result<Vec<MovieNight>, Error>>,
+    ) -> FetchTask {
+        let url = format!("{}/movie_nights", BACKEND);
+        let request = Request::get(url.as_str()).body(Nothing).unwrap();
+        self.fetch_json(request, callback, "listing movie nights")
+    }
+
+    pub fn update_movie_night(
+        &mut self,
+        id: i32,
+        form: &MovieNightForm,
+        callback: Callback<Result<MovieNight, Error>>,
+    ) -> FetchTask {
+        let url = format!("{}/movie_nights/{}", BACKEND, id);
+        let request = Request::put(url.as_str()).body(Json(form)).unwrap();
+        self.fetch_json(request, callback, "updating movie night")
+    }
+
+    /// Cancels a movie night. It is kept, marked as cancelled, so participants can see what happened.
+    pub fn cancel_movie_night(
+        &mut self,
+        id: i32,
+        callback: Callback<Result<MovieNight, Error>>,
+    ) -> FetchTask {
+        let url = format!("{}/movie_nights/{}/cancel", BACKEND, id);
+        let request = Request::post(url.as_str()).body(Nothing).unwrap();
+        self.fetch_json(request, callback, "cancelling movie night")
+    }
+
+    pub fn update_candidates(
+        &mut self,
+        id: i32,
+        choices: &CandidateChoices,
+        callback: Callback<Result<MovieNight, Error>>,
+    ) -> FetchTask {
+        let url = format!("{}/movie_nights/{}/candidates", BACKEND, id);
+        let request = Request::put(url.as_str()).body(Json(choices)).unwrap();
+        self.fetch_json(request, callback, "updating candidates")
+    }
+
+    /// Records whether a participant is coming, replacing their previous answer.
+    pub fn answer_rsvp(
+        &mut self,
+        id: i32,
+        attendance: &Attendance,
+        callback: Callback<Result<MovieNight, Error>>,
+    ) -> FetchTask {
+        let url = format!("{}/movie_nights/{}/rsvps/{}", BACKEND, id, attendance.participant);
+        let request = Request::put(url.as_str()).body(Json(attendance)).unwrap();
+        self.fetch_json(request, callback, "answering rsvp")
+    }
+
+    /// Vetoes a candidate, using one of the voter's veto tokens.
+    pub fn veto_candidate(
+        &mut self,
+        id: i32,
+        veto: &Veto,
+        callback: Callback<Result<MovieNight, Error>>,
+    ) -> FetchTask {
+        let url = format!("{}/movie_nights/{}/vetoes", BACKEND, id);
+        let request = Request::post(url.as_str()).body(Json(veto)).unwrap();
+        self.fetch_json(request, callback, "vetoing candidate")
+    }
+
+    pub fn create_invite(
+        &mut self,
+        movie_night_id: i32,
+        form: &InviteForm,
+        callback: Callback<Result<Invite, Error>>,
+    ) -> FetchTask {
+        let url = format!("{}/movie_nights/{}/invites", BACKEND, movie_night_id);
+        let request = Request::post(url.as_str()).body(Json(form)).unwrap();
+        self.fetch_json(request, callback, "creating invite")
+    }
+
+    pub fn get_invite(
+        &mut self,
+        token: &str,
+        callback: Callback<Result<InviteDetails, Error>>,
+    ) -> FetchTask {
+        let url = format!("{}/invites/{}", BACKEND, token);
+        let request = Request::get(url.as_str()).body(Nothing).unwrap();
+        self.fetch_json(request, callback, "getting invite")
+    }
+
+    /// Adds the user to the participants of the invite's movie night, using up one of its uses.
+    pub fn join_movie_night(
+        &mut self,
+        token: &str,
+        user_id: i32,
+        callback: Callback<Result<MovieNight, Error>>,
+    ) -> FetchTask {
+        let url = format!("{}/invites/{}/join", BACKEND, token);
+        let request = Request::post(url.as_str()).body(Json(&JoinRequest { user_id })).unwrap();
+        self.fetch_json(request, callback, "joining movie night")
+    }
+
+    pub fn sign_up(
+        &mut self,
+        sign_up: &SignUp,
+        callback: Callback<Result<User, Error>>,
+    ) -> FetchTask {
+        let url = format!("{}/users", BACKEND);
+        let request = Request::post(url.as_str()).body(Json(sign_up)).unwrap();
+        self.fetch_json(request, callback, "signing up")
+    }
+
+    /// Records the movie of a movie night in the watch history.
+    pub fn mark_watched(
+        &mut self,
+        id: i32,
+        form: &WatchedForm,
+        callback: Callback<Result<WatchedMovie, Error>>,
+    ) -> FetchTask {
+        let url = format!("{}/movie_nights/{}/watched", BACKEND, id);
+        let request = Request::post(url.as_str()).body(Json(form)).unwrap();
+        self.fetch_json(request, callback, "marking movie night as watched")
+    }
+
+    pub fn get_watch_history(
+        &mut self,
+        callback: Callback<Result<Vec<WatchedMovie>, Error>>,
+    ) -> FetchTask {
+        let url = format!("{}/watch_history", BACKEND);
+        let request = Request::get(url.as_str()).body(Nothing).unwrap();
+        self.fetch_json(request, callback, "getting watch history")
+    }
+
+    pub fn list_ballots(
+        &mut self,
+        id: i32,
+        callback: Callback<Result<Vec<Ballot>, Error>>,
+    ) -> FetchTask {
+        let url = format!("{}/movie_nights/{}/ballots", BACKEND, id);
+        let request = Request::get(url.as_str()).body(Nothing).unwrap();
+        self.fetch_json(request, callback, "listing ballots")
+    }
+
+    /// Casts the ballot of its voter, replacing the one they cast before.
+    pub fn submit_ballot(
+        &mut self,
+        id: i32,
+        ballot: &Ballot,
+        callback: Callback<Result<Ballot, Error>>,
+    ) -> FetchTask {
+        let url = format!("{}/movie_nights/{}/ballots/{}", BACKEND, id, ballot.voter);
+        let request = Request::put(url.as_str()).body(Json(ballot)).unwrap();
+        self.fetch_json(request, callback, "submitting ballot")
+    }
+
+    /// Sends a request and emits its JSON response, or an error naming what we were doing.
+    fn fetch_json<B, T>(
+        &mut self,
+        request: Request<B>,
+        callback: Callback<Result<T, Error>>,
+        doing: &'static str,
+    ) -> FetchTask
+        where B: Into<Text>,
+              T: DeserializeOwned + 'static
+    {
+        let handler = move |response: Response<Json<Result<T, Error>>>| {
+            let (meta, Json(data)) = response.into_parts();
+            if meta.status.is_success() {
+                callback.emit(data)
+            } else {
+                // format_err! is a macro in crate `failure`
+                callback.emit(Err(format_err!(
+                    "{}: error {} on froovie",
+                    meta.status,
+                    doing
+                )))
+            }
+        };
+        self.web.fetch(request, handler.into())
+    }
 }
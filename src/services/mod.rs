@@ -1 +1,2 @@
-pub mod froovie_service;
\ No newline at end of file
+pub mod froovie_service;
+pub mod session;
@@ -0,0 +1,26 @@
+//! The user the app acts as, remembered by the browser across visits.
+
+use failure::Error;
+use yew::services::storage::{Area, StorageService};
+
+use crate::services::froovie_service::DEFAULT_USER_ID;
+
+const USER_ID_KEY: &str = "froovie.user_id";
+
+/// The user who signed up in this browser, if anyone did.
+pub fn signed_in_user() -> Option<i32> {
+    let storage = StorageService::new(Area::Local);
+    let user_id: Result<String, Error> = storage.restore(USER_ID_KEY);
+    user_id.ok().and_then(|user_id| user_id.parse().ok())
+}
+
+/// The user the app acts as: the one who signed up in this browser, or the default user.
+pub fn current_user_id() -> i32 {
+    signed_in_user().unwrap_or(DEFAULT_USER_ID)
+}
+
+/// Acts as the user from now on, in every tab and on the next visits.
+pub fn sign_in(user_id: i32) {
+    let mut storage = StorageService::new(Area::Local);
+    storage.store(USER_ID_KEY, Ok(user_id.to_string()));
+}
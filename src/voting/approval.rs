@@ -0,0 +1,43 @@
+use crate::services::froovie_service::Ballot;
+use crate::voting::{single_round, Results, Tally};
+
+/// Approval voting: each ballot approves any number of candidates, the most approved wins.
+pub struct Approval;
+
+impl Tally for Approval {
+    fn name(&self) -> &'static str {
+        "approval voting"
+    }
+
+    fn unit(&self) -> &'static str {
+        "approvals"
+    }
+
+    fn tally(&self, candidates: &[i32], ballots: &[Ballot]) -> Results {
+        single_round(candidates, ballots, |ballot, candidate| {
+            if ballot.approved.contains(&candidate) { 1 } else { 0 }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::voting::{Outcome, Round};
+
+    fn approving(voter: i32, approved: &[i32]) -> Ballot {
+        Ballot { voter, approved: approved.to_vec(), ..Ballot::default() }
+    }
+
+    #[test]
+    fn the_most_approved_candidate_wins() {
+        let ballots = vec![approving(1, &[1, 2]), approving(2, &[2]), approving(3, &[9]), approving(4, &[])];
+
+        let results = Approval.tally(&[1, 2, 3], &ballots);
+
+        assert_eq!(results, Results {
+            rounds: vec![Round { counts: vec![(2, 2), (1, 1), (3, 0)], exhausted: 2, eliminated: vec![] }],
+            outcome: Outcome::Winner(2),
+        });
+    }
+}
@@ -0,0 +1,196 @@
+use crate::services::froovie_service::Ballot;
+use crate::voting::{distinct, Outcome, Results, Round, Tally};
+
+/// Instant-runoff voting, counting ranked ballots.
+///
+/// Each round, every ballot counts for its highest ranked candidate still in the race.
+/// A candidate with a majority of the ballots that are not exhausted wins,
+/// otherwise the candidates with the fewest votes are all eliminated.
+/// When they are the only ones left, the outcome is a tie between them.
+///
+/// Ballots may rank a candidate twice: the second entry is skipped.
+pub struct InstantRunoff;
+
+impl Tally for InstantRunoff {
+    fn name(&self) -> &'static str {
+        "instant-runoff voting"
+    }
+
+    fn unit(&self) -> &'static str {
+        "votes"
+    }
+
+    fn tally(&self, candidates: &[i32], ballots: &[Ballot]) -> Results {
+        let mut remaining = distinct(candidates);
+        let mut rounds = Vec::new();
+
+        loop {
+            if remaining.is_empty() {
+                return Results { rounds, outcome: Outcome::NoVotes };
+            }
+
+            let mut counts: Vec<(i32, usize)> = remaining.iter().map(|&candidate| (candidate, 0)).collect();
+            let mut exhausted = 0;
+            for ballot in ballots {
+                match ballot.ranking.iter().find(|choice| remaining.contains(choice)) {
+                    Some(choice) => {
+                        if let Some(count) = counts.iter_mut().find(|(candidate, _)| candidate == choice) {
+                            count.1 += 1;
+                        }
+                    }
+                    None => exhausted += 1,
+                }
+            }
+            // Stable, so candidates with as many votes keep the order they were given in.
+            counts.sort_by(|a, b| b.1.cmp(&a.1));
+
+            let active = ballots.len() - exhausted;
+            if active == 0 {
+                rounds.push(Round { counts, exhausted, eliminated: vec![] });
+                return Results { rounds, outcome: Outcome::NoVotes };
+            }
+
+            let majority = counts.iter().find(|(_, votes)| 2 * votes > active).map(|(candidate, _)| *candidate);
+            if let Some(winner) = majority {
+                rounds.push(Round { counts, exhausted, eliminated: vec![] });
+                return Results { rounds, outcome: Outcome::Winner(winner) };
+            }
+
+            let fewest = counts.iter().map(|(_, votes)| *votes).min().unwrap_or(0);
+            let eliminated: Vec<i32> = counts.iter()
+                .filter(|(_, votes)| *votes == fewest)
+                .map(|(candidate, _)| *candidate)
+                .collect();
+            if eliminated.len() == remaining.len() {
+                rounds.push(Round { counts, exhausted, eliminated: vec![] });
+                return Results { rounds, outcome: Outcome::Tie(eliminated) };
+            }
+
+            remaining.retain(|candidate| !eliminated.contains(candidate));
+            rounds.push(Round { counts, exhausted, eliminated });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ranked(voter: i32, ranking: &[i32]) -> Ballot {
+        Ballot { voter, ranking: ranking.to_vec(), ..Ballot::default() }
+    }
+
+    fn round(counts: &[(i32, usize)], exhausted: usize, eliminated: &[i32]) -> Round {
+        Round { counts: counts.to_vec(), exhausted, eliminated: eliminated.to_vec() }
+    }
+
+    #[test]
+    fn majority_in_the_first_round_wins() {
+        let ballots = vec![ranked(1, &[1, 2]), ranked(2, &[1, 3]), ranked(3, &[2, 1])];
+
+        let results = InstantRunoff.tally(&[1, 2, 3], &ballots);
+
+        assert_eq!(results, Results {
+            rounds: vec![round(&[(1, 2), (2, 1), (3, 0)], 0, &[])],
+            outcome: Outcome::Winner(1),
+        });
+    }
+
+    #[test]
+    fn candidates_tied_for_fewest_votes_are_eliminated_together() {
+        let ballots = vec![
+            ranked(1, &[1]),
+            ranked(2, &[1]),
+            ranked(3, &[2]),
+            ranked(4, &[2]),
+            ranked(5, &[3, 2]),
+            ranked(6, &[4, 2]),
+        ];
+
+        let results = InstantRunoff.tally(&[1, 2, 3, 4], &ballots);
+
+        assert_eq!(results, Results {
+            rounds: vec![
+                round(&[(1, 2), (2, 2), (3, 1), (4, 1)], 0, &[3, 4]),
+                round(&[(2, 4), (1, 2)], 0, &[]),
+            ],
+            outcome: Outcome::Winner(2),
+        });
+    }
+
+    #[test]
+    fn exhausted_ballots_do_not_count_towards_the_majority() {
+        let ballots = vec![
+            ranked(1, &[1]),
+            ranked(2, &[1]),
+            ranked(3, &[1]),
+            ranked(4, &[2]),
+            ranked(5, &[2]),
+            ranked(6, &[3]),
+        ];
+
+        let results = InstantRunoff.tally(&[1, 2, 3], &ballots);
+
+        // 3 votes out of 6 is no majority, but 3 out of the 5 ballots left is.
+        assert_eq!(results, Results {
+            rounds: vec![
+                round(&[(1, 3), (2, 2), (3, 1)], 0, &[3]),
+                round(&[(1, 3), (2, 2)], 1, &[]),
+            ],
+            outcome: Outcome::Winner(1),
+        });
+    }
+
+    #[test]
+    fn candidates_all_tied_for_fewest_votes_are_a_tie() {
+        let ballots = vec![ranked(1, &[1, 2]), ranked(2, &[2, 3]), ranked(3, &[3, 1])];
+
+        let results = InstantRunoff.tally(&[1, 2, 3], &ballots);
+
+        assert_eq!(results, Results {
+            rounds: vec![round(&[(1, 1), (2, 1), (3, 1)], 0, &[])],
+            outcome: Outcome::Tie(vec![1, 2, 3]),
+        });
+    }
+
+    #[test]
+    fn duplicates_count_once() {
+        let ballots = vec![ranked(1, &[1, 1]), ranked(2, &[2]), ranked(3, &[2, 1, 2])];
+
+        let results = InstantRunoff.tally(&[1, 2, 1], &ballots);
+
+        assert_eq!(results, Results {
+            rounds: vec![round(&[(2, 2), (1, 1)], 0, &[])],
+            outcome: Outcome::Winner(2),
+        });
+    }
+
+    #[test]
+    fn rankings_skip_unknown_candidates() {
+        let ballots = vec![ranked(1, &[9, 1]), ranked(2, &[9]), ranked(3, &[2])];
+
+        let results = InstantRunoff.tally(&[1, 2], &ballots);
+
+        assert_eq!(results, Results {
+            rounds: vec![round(&[(1, 1), (2, 1)], 1, &[])],
+            outcome: Outcome::Tie(vec![1, 2]),
+        });
+    }
+
+    #[test]
+    fn no_ballots_is_no_votes() {
+        let results = InstantRunoff.tally(&[1, 2], &[]);
+
+        assert_eq!(results, Results {
+            rounds: vec![round(&[(1, 0), (2, 0)], 0, &[])],
+            outcome: Outcome::NoVotes,
+        });
+    }
+
+    #[test]
+    fn no_candidates_is_no_votes() {
+        let results = InstantRunoff.tally(&[], &[ranked(1, &[1])]);
+
+        assert_eq!(results, Results { rounds: vec![], outcome: Outcome::NoVotes });
+    }
+}
@@ -0,0 +1,123 @@
+//! Tallying of the ballots cast on a movie night.
+//!
+//! Each voting method the host can pick implements `Tally`,
+//! so the vote and results views work the same with any of them.
+
+pub mod approval;
+pub mod instant_runoff;
+pub mod plurality;
+pub mod score;
+pub mod tie_break;
+
+use crate::services::froovie_service::{Ballot, VotingMethod};
+
+pub use self::approval::Approval;
+pub use self::instant_runoff::InstantRunoff;
+pub use self::plurality::Plurality;
+pub use self::score::Score;
+
+/// One counting round, with the votes of every candidate still in the race.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Round {
+    /// Votes per candidate moviedb id, from the most to the least voted.
+    pub counts: Vec<(i32, usize)>,
+    /// Ballots counting for none of the remaining candidates.
+    pub exhausted: usize,
+    /// Candidates eliminated at the end of the round.
+    pub eliminated: Vec<i32>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Outcome {
+    Winner(i32),
+    /// The remaining candidates could not be separated.
+    Tie(Vec<i32>),
+    NoVotes,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Results {
+    pub rounds: Vec<Round>,
+    pub outcome: Outcome,
+}
+
+/// A way of counting ballots.
+pub trait Tally {
+    /// The name of the method, shown with the results.
+    fn name(&self) -> &'static str;
+
+    /// What the counts of a round are, e.g. "votes" or "points".
+    fn unit(&self) -> &'static str;
+
+    /// Counts the ballots. Entries of the ballots that are not in `candidates` are skipped.
+    fn tally(&self, candidates: &[i32], ballots: &[Ballot]) -> Results;
+}
+
+/// The implementation of a voting method.
+pub fn tally_for(method: VotingMethod) -> Box<dyn Tally> {
+    match method {
+        VotingMethod::Ranked => Box::new(InstantRunoff),
+        VotingMethod::Approval => Box::new(Approval),
+        VotingMethod::Score => Box::new(Score),
+        VotingMethod::Plurality => Box::new(Plurality),
+    }
+}
+
+/// The winner of the vote on a movie night, drawn among the tied candidates if there is a tie.
+pub fn winner(movie_night_id: i32, method: VotingMethod, candidates: &[i32], ballots: &[Ballot]) -> Option<i32> {
+    match tally_for(method).tally(candidates, ballots).outcome {
+        Outcome::Winner(winner) => Some(winner),
+        Outcome::Tie(tied) => tie_break::draw(movie_night_id, ballots, &tied).map(|tie_break| tie_break.winner),
+        Outcome::NoVotes => None,
+    }
+}
+
+/// The candidates, without duplicates, in the order they were given in.
+fn distinct(candidates: &[i32]) -> Vec<i32> {
+    let mut distinct = Vec::new();
+    for &candidate in candidates {
+        if !distinct.contains(&candidate) {
+            distinct.push(candidate);
+        }
+    }
+    distinct
+}
+
+/// Counts the ballots in a single round, adding up the points each ballot gives to each candidate.
+/// The candidates with the most points win, ballots giving no point to any candidate are exhausted.
+fn single_round<F>(candidates: &[i32], ballots: &[Ballot], points: F) -> Results
+    where F: Fn(&Ballot, i32) -> usize
+{
+    let candidates = distinct(candidates);
+    let mut counts: Vec<(i32, usize)> = candidates.iter().map(|&candidate| (candidate, 0)).collect();
+    let mut exhausted = 0;
+    for ballot in ballots {
+        let mut counted = false;
+        for count in counts.iter_mut() {
+            let points = points(ballot, count.0);
+            count.1 += points;
+            counted = counted || points > 0;
+        }
+        if !counted {
+            exhausted += 1;
+        }
+    }
+    // Stable, so candidates with as many points keep the order they were given in.
+    counts.sort_by(|a, b| b.1.cmp(&a.1));
+
+    let most = counts.first().map(|(_, points)| *points).unwrap_or(0);
+    let leaders: Vec<i32> = counts.iter()
+        .filter(|(_, points)| *points == most)
+        .map(|(candidate, _)| *candidate)
+        .collect();
+    let outcome = match leaders.len() {
+        _ if most == 0 => Outcome::NoVotes,
+        1 => Outcome::Winner(leaders[0]),
+        _ => Outcome::Tie(leaders),
+    };
+
+    Results {
+        rounds: vec![Round { counts, exhausted, eliminated: vec![] }],
+        outcome,
+    }
+}
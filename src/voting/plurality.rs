@@ -0,0 +1,58 @@
+use crate::services::froovie_service::Ballot;
+use crate::voting::{single_round, Results, Tally};
+
+/// Plurality voting: each ballot chooses one candidate, the most chosen wins.
+pub struct Plurality;
+
+impl Tally for Plurality {
+    fn name(&self) -> &'static str {
+        "plurality voting"
+    }
+
+    fn unit(&self) -> &'static str {
+        "votes"
+    }
+
+    fn tally(&self, candidates: &[i32], ballots: &[Ballot]) -> Results {
+        single_round(candidates, ballots, |ballot, candidate| {
+            if ballot.choice == Some(candidate) { 1 } else { 0 }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::voting::{Outcome, Round};
+
+    fn choosing(voter: i32, choice: Option<i32>) -> Ballot {
+        Ballot { voter, choice, ..Ballot::default() }
+    }
+
+    #[test]
+    fn the_most_chosen_candidate_wins() {
+        let ballots = vec![
+            choosing(1, Some(1)),
+            choosing(2, Some(2)),
+            choosing(3, Some(2)),
+            choosing(4, None),
+            choosing(5, Some(9)),
+        ];
+
+        let results = Plurality.tally(&[1, 2], &ballots);
+
+        assert_eq!(results, Results {
+            rounds: vec![Round { counts: vec![(2, 2), (1, 1)], exhausted: 2, eliminated: vec![] }],
+            outcome: Outcome::Winner(2),
+        });
+    }
+
+    #[test]
+    fn candidates_with_as_many_votes_are_a_tie() {
+        let ballots = vec![choosing(1, Some(2)), choosing(2, Some(1))];
+
+        let results = Plurality.tally(&[1, 2, 3], &ballots);
+
+        assert_eq!(results.outcome, Outcome::Tie(vec![1, 2]));
+    }
+}
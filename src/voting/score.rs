@@ -0,0 +1,52 @@
+use crate::services::froovie_service::{Ballot, MAX_SCORE};
+use crate::voting::{single_round, Results, Tally};
+
+/// Score voting: each ballot gives every candidate from 0 to `MAX_SCORE` stars, the most stars win.
+///
+/// Scores above `MAX_SCORE` count as `MAX_SCORE`, a candidate scored twice keeps its first score.
+pub struct Score;
+
+impl Tally for Score {
+    fn name(&self) -> &'static str {
+        "score voting"
+    }
+
+    fn unit(&self) -> &'static str {
+        "stars"
+    }
+
+    fn tally(&self, candidates: &[i32], ballots: &[Ballot]) -> Results {
+        single_round(candidates, ballots, |ballot, candidate| {
+            ballot.scores.iter()
+                .find(|(scored, _)| *scored == candidate)
+                .map(|(_, score)| usize::from((*score).min(MAX_SCORE)))
+                .unwrap_or(0)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::voting::{Outcome, Round};
+
+    fn scoring(voter: i32, scores: &[(i32, u8)]) -> Ballot {
+        Ballot { voter, scores: scores.to_vec(), ..Ballot::default() }
+    }
+
+    #[test]
+    fn the_most_stars_win_with_scores_clamped_and_counted_once() {
+        let ballots = vec![
+            scoring(1, &[(1, 5), (2, 3)]),
+            scoring(2, &[(2, 9), (1, 1), (2, 0)]),
+            scoring(3, &[(1, 0)]),
+        ];
+
+        let results = Score.tally(&[1, 2], &ballots);
+
+        assert_eq!(results, Results {
+            rounds: vec![Round { counts: vec![(2, 8), (1, 6)], exhausted: 1, eliminated: vec![] }],
+            outcome: Outcome::Winner(2),
+        });
+    }
+}
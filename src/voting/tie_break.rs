@@ -0,0 +1,127 @@
+//! Breaking ties with a draw anyone can check.
+//!
+//! The draw is seeded from the movie night and its ballots only,
+//! so every participant recomputes the same winner in their own browser.
+
+use crate::services::froovie_service::Ballot;
+
+/// How the winner of a tie is drawn, shown with the results.
+pub const PROCEDURE: &str = "The seed is the 64-bit FNV-1a hash of the seed input: \
+    the movie night id, then every ballot sorted by voter, as \
+    `;voter:ranking/approved/scores/choice` with approvals and scores sorted by moviedb id. \
+    The tied candidates are sorted by moviedb id, and the winner is the one at index \
+    `splitmix64(seed) % number of tied candidates`.";
+
+const FNV_OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+const FNV_PRIME: u64 = 0x0100_0000_01b3;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct TieBreak {
+    /// The text the seed is the hash of.
+    pub seed_input: String,
+    pub seed: u64,
+    /// The tied candidates, sorted by moviedb id.
+    pub tied: Vec<i32>,
+    pub winner: i32,
+}
+
+fn join<T: ToString>(values: &[T]) -> String {
+    values.iter().map(T::to_string).collect::<Vec<String>>().join(",")
+}
+
+/// The canonical text of a movie night's ballots, independent of the order they were received in.
+pub fn seed_input(movie_night_id: i32, ballots: &[Ballot]) -> String {
+    let mut ballots: Vec<&Ballot> = ballots.iter().collect();
+    ballots.sort_by_key(|ballot| ballot.voter);
+
+    let mut input = movie_night_id.to_string();
+    for ballot in ballots {
+        let mut approved = ballot.approved.clone();
+        approved.sort();
+        let mut scores = ballot.scores.clone();
+        scores.sort();
+        let scores: Vec<String> = scores.iter().map(|(moviedb_id, score)| format!("{}={}", moviedb_id, score)).collect();
+        let choice = ballot.choice.map(|choice| choice.to_string()).unwrap_or_default();
+        input.push_str(&format!(";{}:{}/{}/{}/{}", ballot.voter, join(&ballot.ranking), join(&approved), scores.join(","), choice));
+    }
+    input
+}
+
+/// The 64-bit FNV-1a hash of the bytes of the text.
+pub fn fnv1a(text: &str) -> u64 {
+    text.bytes().fold(FNV_OFFSET_BASIS, |hash, byte| (hash ^ u64::from(byte)).wrapping_mul(FNV_PRIME))
+}
+
+/// The first output of the SplitMix64 generator seeded with `seed`.
+pub fn splitmix64(seed: u64) -> u64 {
+    let mut z = seed.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+/// Draws the winner among the tied candidates, or `None` if there are none.
+pub fn draw(movie_night_id: i32, ballots: &[Ballot], tied: &[i32]) -> Option<TieBreak> {
+    let mut tied = tied.to_vec();
+    tied.sort();
+    tied.dedup();
+    if tied.is_empty() {
+        return None;
+    }
+
+    let seed_input = seed_input(movie_night_id, ballots);
+    let seed = fnv1a(&seed_input);
+    let winner = tied[(splitmix64(seed) % tied.len() as u64) as usize];
+    Some(TieBreak { seed_input, seed, tied, winner })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ballots() -> Vec<Ballot> {
+        vec![
+            Ballot {
+                voter: 2,
+                ranking: vec![3, 1],
+                approved: vec![3, 1],
+                scores: vec![(3, 2), (1, 4)],
+                choice: Some(3),
+            },
+            Ballot { voter: 1, ..Ballot::default() },
+        ]
+    }
+
+    #[test]
+    fn seed_input_is_canonical() {
+        assert_eq!(seed_input(7, &ballots()), "7;1:///;2:3,1/1,3/1=4,3=2/3");
+    }
+
+    #[test]
+    fn hashes_match_the_reference_values() {
+        assert_eq!(fnv1a(""), FNV_OFFSET_BASIS);
+        assert_eq!(fnv1a("a"), 0xaf63_dc4c_8601_ec8c);
+        assert_eq!(splitmix64(0), 0xe220_a839_7b1d_cdaf);
+    }
+
+    #[test]
+    fn draw_is_deterministic_and_ignores_the_order_of_the_ballots() {
+        let ballots = ballots();
+        let mut reversed = ballots.clone();
+        reversed.reverse();
+
+        let tie_break = draw(7, &ballots, &[3, 1, 3]).expect("there are tied candidates");
+
+        assert_eq!(tie_break.tied, vec![1, 3]);
+        assert_eq!(tie_break.seed_input, seed_input(7, &ballots));
+        assert_eq!(tie_break.seed, fnv1a(&tie_break.seed_input));
+        assert_eq!(tie_break.winner, tie_break.tied[(splitmix64(tie_break.seed) % 2) as usize]);
+        assert_eq!(draw(7, &ballots, &[1, 3]), Some(tie_break.clone()));
+        assert_eq!(draw(7, &reversed, &[3, 1]), Some(tie_break));
+    }
+
+    #[test]
+    fn nothing_to_draw_without_tied_candidates() {
+        assert_eq!(draw(7, &ballots(), &[]), None);
+    }
+}